@@ -1,11 +1,26 @@
 use crate::config::{
-    ConclaudeConfig, SubagentStopConfig, extract_bash_commands, load_conclaude_config,
+    ConclaudeConfig, ContentRule, GeneratedFileMarker, GeneratedFilesConfig, NotebookCellRule,
+    NotificationRoute, NotificationSink, PostToolUseCommand, PostToolUseConfig, PromptContextRule,
+    PromptRule, RewriteRule, SessionEndCommand, SessionEndConfig, SessionStartCommand,
+    SessionStartConfig, SubagentStopConfig, UnEditableFileRule, WriteLimitsConfig,
+    extract_bash_commands, load_conclaude_config, parse_local_http_url,
 };
 use crate::gitignore::{find_git_root, is_path_git_ignored};
+use crate::permissions::{
+    MatchContext, PermissionRule, evaluate_mcp_policy, resolve_path, tool_name_matches, value_text,
+};
+use crate::quotas;
+use crate::secrets::SecretScanner;
+use crate::shell::{
+    WriteTarget, extract_read_targets, extract_write_targets, simple_command_texts,
+};
+use crate::trace;
 use crate::types::{
-    HookResult, NotificationPayload, PermissionRequestPayload, PostToolUsePayload,
-    PreCompactPayload, PreToolUsePayload, SessionEndPayload, SessionStartPayload, StopPayload,
-    SubagentStartPayload, SubagentStopPayload, UserPromptSubmitPayload, validate_base_payload,
+    BasePayload, CompactTrigger, EditOperation, HookResult, HookSpecificOutput, MultiEditInput,
+    NotebookEditInput, NotebookEditMode, NotificationPayload, PermissionBehavior,
+    PermissionDecision, PermissionRequestPayload, PostToolUsePayload, PreCompactPayload,
+    PreToolUsePayload, SessionEndPayload, SessionStartPayload, StopPayload, SubagentStartPayload,
+    SubagentStopPayload, UserPromptSubmitPayload, validate_base_payload,
     validate_permission_request_payload, validate_subagent_start_payload,
     validate_subagent_stop_payload,
 };
@@ -16,7 +31,7 @@ use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{self, Read};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::OnceLock;
 use tokio::process::Command as TokioCommand;
@@ -115,6 +130,11 @@ fn get_config_dir(config_path: &Path) -> &Path {
 /// * `status` - The execution status ("success" or "failure")
 /// * `context` - Optional additional context about the execution
 fn send_notification(hook_name: &str, status: &str, context: Option<&str>) {
+    // `conclaude explain` replays hooks without side effects
+    if trace::is_active() {
+        return;
+    }
+
     // Get configuration to check if notifications are enabled for this hook
     let config_future = get_config();
 
@@ -217,13 +237,14 @@ where
 
 /// Wrapper function that standardizes hook result processing and process exit codes.
 ///
-/// # Errors
-///
-/// Returns an error if the hook handler fails to execute.
+/// Results with structured fields are written to stdout as Claude Code's JSON hook
+/// output and exit 0. Plain results keep the exit code protocol: 0 to proceed, or the
+/// message on stderr with exit 2 to block. Diagnostic logging from the handlers goes
+/// to stderr so stdout stays reserved for the JSON output.
 ///
-/// # Panics
+/// # Errors
 ///
-/// This function does not panic - the `unwrap()` call is guarded by `is_some()` check.
+/// Returns an error if the hook handler fails to execute or its output cannot be serialized.
 pub async fn handle_hook_result<F, Fut>(handler: F) -> Result<()>
 where
     F: FnOnce() -> Fut,
@@ -231,8 +252,14 @@ where
 {
     match handler().await {
         Ok(result) => {
-            if result.blocked.unwrap_or(false) && result.message.is_some() {
-                eprintln!("{}", result.message.unwrap());
+            if result.has_json_output() {
+                let output =
+                    serde_json::to_string(&result).context("Failed to serialize hook output")?;
+                println!("{output}");
+                std::process::exit(0);
+            }
+            if let (Some(true), Some(message)) = (result.blocked, &result.message) {
+                eprintln!("{message}");
                 std::process::exit(2);
             }
             std::process::exit(0);
@@ -251,36 +278,137 @@ where
 /// Returns an error if payload validation fails or configuration loading fails.
 pub async fn handle_pre_tool_use() -> Result<HookResult> {
     let payload: PreToolUsePayload = read_payload_from_stdin()?;
+    process_pre_tool_use(payload, true).await
+}
 
+/// Runs the `PreToolUse` checks for an already-parsed payload.
+///
+/// Shared by the live hook and `conclaude explain`. `record_quotas` is false for
+/// `explain`, which reports quota usage without counting the call.
+///
+/// # Errors
+///
+/// Returns an error if payload validation fails or configuration loading fails.
+pub async fn process_pre_tool_use(
+    mut payload: PreToolUsePayload,
+    record_quotas: bool,
+) -> Result<HookResult> {
     validate_base_payload(&payload.base).map_err(|e| anyhow::anyhow!(e))?;
 
     if payload.tool_name.is_empty() {
         return Err(anyhow::anyhow!("Missing required field: tool_name"));
     }
 
-    println!(
+    eprintln!(
         "Processing PreToolUse hook: session_id={}, tool_name={}",
         payload.base.session_id, payload.tool_name
     );
 
-    // Check tool usage validation rules
-    if let Some(result) = check_tool_usage_rules(&payload).await? {
+    // Apply rewrite rules first so validation sees the input that will actually run
+    let (config, config_path) = get_config().await?;
+    let config_dir = get_config_dir(config_path);
+    let rewrite = apply_rewrite_rules(
+        &config.pre_tool_use.rewrite,
+        &payload.tool_name,
+        &payload.tool_input,
+        config_dir,
+    )?;
+    if let Some(rewrite) = &rewrite {
+        eprintln!(
+            "PreToolUse input rewritten: tool_name={}, rules={}",
+            payload.tool_name,
+            rewrite.messages.len()
+        );
+        payload.tool_input.clone_from(&rewrite.updated_input);
+    }
+
+    let session_id = payload.base.session_id.clone();
+    let tool_name = payload.tool_name.clone();
+    let mut enforcement = Enforcement {
+        config,
+        config_dir,
+        hook: "PreToolUse",
+        session_id: &session_id,
+        tool_name: Some(&tool_name),
+        warnings: Vec::new(),
+    };
+
+    // Check tool usage validation rules. An ask is held back until every deny check
+    // has run, so a rule asking for confirmation never hides a block.
+    let mut pending_ask = None;
+    if let Some(result) = check_tool_usage_rules(&payload)
+        .await?
+        .and_then(|result| enforcement.enforce("deny", result))
+    {
+        if result.blocked == Some(true) {
+            send_notification(
+                "PreToolUse",
+                "failure",
+                Some(&format!(
+                    "Tool '{}' blocked by validation rules",
+                    payload.tool_name
+                )),
+            );
+            return Ok(deny_tool_use(result));
+        }
+        pending_ask = Some(result);
+    }
+
+    // Keep every read and write inside the project
+    if let Some(result) = check_workspace_boundary(&payload)
+        .await?
+        .and_then(|result| enforcement.enforce("deny", result))
+    {
         send_notification(
             "PreToolUse",
             "failure",
             Some(&format!(
-                "Tool '{}' blocked by validation rules",
+                "Workspace boundary blocked tool '{}'",
                 payload.tool_name
             )),
         );
-        return Ok(result);
+        return Ok(deny_tool_use(result));
+    }
+
+    // Block reads of protected files before anything else looks at the tool input
+    if let Some(result) = check_unreadable_files(&payload)
+        .await?
+        .and_then(|result| enforcement.enforce("deny", result))
+    {
+        send_notification(
+            "PreToolUse",
+            "failure",
+            Some(&format!(
+                "Read protection blocked tool '{}'",
+                payload.tool_name
+            )),
+        );
+        return Ok(deny_tool_use(result));
+    }
+
+    // Bash commands that write files go through the same file protections
+    if payload.tool_name == "Bash" {
+        if let Some(result) = check_bash_write_targets(&payload)
+            .await?
+            .and_then(|result| enforcement.enforce("deny", result))
+        {
+            send_notification(
+                "PreToolUse",
+                "failure",
+                Some("File protection blocked a Bash command"),
+            );
+            return Ok(deny_tool_use(result));
+        }
     }
 
     let file_modifying_tools = ["Write", "Edit", "MultiEdit", "NotebookEdit"];
 
     if file_modifying_tools.contains(&payload.tool_name.as_str()) {
         // Check if file is git-ignored and should not be modified
-        if let Some(result) = check_git_ignored_file(&payload).await? {
+        if let Some(result) = check_git_ignored_file(&payload)
+            .await?
+            .and_then(|result| enforcement.enforce("deny", result))
+        {
             send_notification(
                 "PreToolUse",
                 "failure",
@@ -289,10 +417,13 @@ pub async fn handle_pre_tool_use() -> Result<HookResult> {
                     payload.tool_name
                 )),
             );
-            return Ok(result);
+            return Ok(deny_tool_use(result));
         }
 
-        if let Some(result) = check_file_validation_rules(&payload).await? {
+        if let Some(result) = check_file_validation_rules(&payload)
+            .await?
+            .and_then(|result| enforcement.enforce("deny", result))
+        {
             send_notification(
                 "PreToolUse",
                 "failure",
@@ -301,11 +432,41 @@ pub async fn handle_pre_tool_use() -> Result<HookResult> {
                     payload.tool_name
                 )),
             );
-            return Ok(result);
+            return Ok(deny_tool_use(result));
+        }
+
+        if let Some(result) = check_notebook_cell_rules(&payload)
+            .await?
+            .and_then(|result| enforcement.enforce("deny", result))
+        {
+            send_notification(
+                "PreToolUse",
+                "failure",
+                Some("Notebook cell protection blocked NotebookEdit"),
+            );
+            return Ok(deny_tool_use(result));
+        }
+
+        if let Some(result) = check_write_limits_for_payload(&payload)
+            .await?
+            .and_then(|result| enforcement.enforce("deny", result))
+        {
+            send_notification(
+                "PreToolUse",
+                "failure",
+                Some(&format!(
+                    "Write limits blocked tool '{}'",
+                    payload.tool_name
+                )),
+            );
+            return Ok(deny_tool_use(result));
         }
 
         // Check if file is auto-generated and should not be edited
-        if let Some(result) = check_auto_generated_file(&payload).await? {
+        if let Some(result) = check_auto_generated_file(&payload)
+            .await?
+            .and_then(|result| enforcement.enforce("deny", result))
+        {
             send_notification(
                 "PreToolUse",
                 "failure",
@@ -314,8 +475,90 @@ pub async fn handle_pre_tool_use() -> Result<HookResult> {
                     payload.tool_name
                 )),
             );
-            return Ok(result);
+            return Ok(deny_tool_use(result));
+        }
+
+        if let Some(result) = check_secret_content(&payload)
+            .await?
+            .and_then(|result| enforcement.enforce("deny", result))
+        {
+            send_notification(
+                "PreToolUse",
+                "failure",
+                Some(&format!(
+                    "Secret scanning blocked tool '{}'",
+                    payload.tool_name
+                )),
+            );
+            return Ok(deny_tool_use(result));
+        }
+
+        let screening = check_content_rules(&payload).await?;
+        if let Some(result) = screening
+            .block_reason
+            .map(HookResult::blocked)
+            .and_then(|result| enforcement.enforce("deny", result))
+        {
+            send_notification(
+                "PreToolUse",
+                "failure",
+                Some(&format!(
+                    "Content rules blocked tool '{}'",
+                    payload.tool_name
+                )),
+            );
+            return Ok(deny_tool_use(result));
         }
+        enforcement.warnings.extend(screening.warnings);
+    }
+
+    // Quotas run last so only calls that are otherwise allowed use them up. Calls the
+    // user is asked to confirm may still be refused, so they are checked but not counted.
+    let asks_user = pending_ask.is_some()
+        || rewrite
+            .as_ref()
+            .is_some_and(|rewrite| rewrite.decision == PermissionDecision::Ask);
+    if let Some(result) = check_session_quotas(&payload, record_quotas && !asks_user)
+        .await?
+        .and_then(|result| enforcement.enforce("deny", result))
+    {
+        send_notification(
+            "PreToolUse",
+            "failure",
+            Some(&format!(
+                "Session quota blocked tool '{}'",
+                payload.tool_name
+            )),
+        );
+        return Ok(deny_tool_use(result));
+    }
+
+    if let Some(result) = pending_ask {
+        let result = match &rewrite {
+            Some(rewrite) => result.with_updated_input(rewrite.updated_input.clone()),
+            None => result,
+        };
+        send_notification(
+            "PreToolUse",
+            "success",
+            Some(&format!(
+                "Tool '{}' requires confirmation by validation rules",
+                payload.tool_name
+            )),
+        );
+        return Ok(with_pre_tool_use_warnings(result, &enforcement.warnings));
+    }
+
+    if let Some(rewrite) = rewrite {
+        send_notification(
+            "PreToolUse",
+            "success",
+            Some(&format!("Tool '{}' input rewritten", payload.tool_name)),
+        );
+        let reason = Some(rewrite.messages.join("\n")).filter(|m| !m.is_empty());
+        let result = HookResult::pre_tool_use_decision(rewrite.decision, reason)
+            .with_updated_input(rewrite.updated_input);
+        return Ok(with_pre_tool_use_warnings(result, &enforcement.warnings));
     }
 
     // Send notification for successful pre-tool-use validation
@@ -324,7 +567,187 @@ pub async fn handle_pre_tool_use() -> Result<HookResult> {
         "success",
         Some(&format!("Tool '{}' approved", payload.tool_name)),
     );
-    Ok(HookResult::success())
+    Ok(with_pre_tool_use_warnings(
+        HookResult::success(),
+        &enforcement.warnings,
+    ))
+}
+
+/// Attach warnings to a `PreToolUse` result
+///
+/// Warnings are shown to the user as a system message and given to Claude as
+/// additional context; the tool call still proceeds.
+fn with_pre_tool_use_warnings(mut result: HookResult, warnings: &[String]) -> HookResult {
+    if warnings.is_empty() {
+        return result;
+    }
+    let message = warnings.join("\n");
+    let output = result
+        .hook_specific_output
+        .get_or_insert_with(|| HookSpecificOutput::new("PreToolUse"));
+    output.additional_context = Some(message.clone());
+    result.with_system_message(message)
+}
+
+/// Find the first `permissionRequest` rule in `rules` that matches the requested tool call
+///
+/// # Errors
+///
+/// Returns an error if a rule cannot be parsed or contains an invalid glob pattern.
+fn find_permission_rule_match<'a>(
+    rules: &'a [String],
+    list_name: &str,
+    payload: &PermissionRequestPayload,
+    context: &MatchContext,
+    require_all: bool,
+) -> Result<Option<&'a String>> {
+    for (idx, rule) in rules.iter().enumerate() {
+        let parsed = PermissionRule::parse(rule).with_context(|| {
+            format!("Invalid rule in permissionRequest.{list_name} list: {rule}")
+        })?;
+        let matched = parsed.matches(
+            &payload.tool_name,
+            &payload.tool_input,
+            context,
+            require_all,
+        )?;
+        trace::record(
+            format!("permissionRequest.{list_name}[{idx}]"),
+            format!("'{rule}' against {}", payload.tool_name),
+            matched,
+        );
+        if matched {
+            return Ok(Some(rule));
+        }
+    }
+    Ok(None)
+}
+
+/// Report a blocked `PreToolUse` check as a `deny` permission decision
+#[must_use]
+fn deny_tool_use(result: HookResult) -> HookResult {
+    HookResult::pre_tool_use_decision(PermissionDecision::Deny, result.message)
+}
+
+/// Default audit log file name, placed in the system temp directory
+const DEFAULT_AUDIT_LOG: &str = "conclaude-audit.jsonl";
+
+/// Path of the audit log: `auditLog` relative to the config file, or the temp directory
+#[must_use]
+pub fn audit_log_path(config: &ConclaudeConfig, config_dir: &Path) -> PathBuf {
+    config.audit_log.as_ref().map_or_else(
+        || std::env::temp_dir().join(DEFAULT_AUDIT_LOG),
+        |path| config_dir.join(path),
+    )
+}
+
+/// Decides what happens to blocked results from rules that may not actually block
+///
+/// Blocked results pass through [`Enforcement::enforce`]. Results from `severity: warn`
+/// rules become warnings for Claude. When the rule's `mode` (or the top-level `mode`) is
+/// "audit", the decision is appended to the audit log and dropped. Either way the hook
+/// carries on with its remaining checks and lets the call through.
+struct Enforcement<'a> {
+    config: &'a ConclaudeConfig,
+    config_dir: &'a Path,
+    hook: &'static str,
+    session_id: &'a str,
+    tool_name: Option<&'a str>,
+    warnings: Vec<String>,
+}
+
+impl Enforcement<'_> {
+    /// Returns the result to act on, or `None` when a block became a warning or was
+    /// logged for audit instead
+    fn enforce(&mut self, decision: &str, mut result: HookResult) -> Option<HookResult> {
+        for soft_block in std::mem::take(&mut result.deferred) {
+            self.enforce(decision, soft_block);
+        }
+        if result.blocked != Some(true) {
+            return Some(result);
+        }
+        if result.is_warning() {
+            let message = result.message.unwrap_or_default();
+            trace::record("severity", format!("warn: {message}"), true);
+            self.warnings.push(message);
+            return None;
+        }
+        if !self.config.is_audit(result.mode.as_deref()) {
+            return Some(result);
+        }
+
+        let reason = result.message.unwrap_or_default();
+        eprintln!(
+            "Audit mode: {} would {decision}: {}",
+            self.hook,
+            reason.replace('\n', " ")
+        );
+        trace::record("mode", format!("audit: would {decision}: {reason}"), true);
+        // `conclaude explain` reports the decision without writing it
+        if !trace::is_active() {
+            if let Err(e) = self.write(decision, &reason) {
+                eprintln!("Failed to write audit log: {e}");
+            }
+        }
+        None
+    }
+
+    fn write(&self, decision: &str, reason: &str) -> Result<()> {
+        let path = audit_log_path(self.config, self.config_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let entry = serde_json::json!({
+            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "sessionId": self.session_id,
+            "hook": self.hook,
+            "toolName": self.tool_name,
+            "decision": decision,
+            "reason": reason,
+        });
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open audit log: {}", path.display()))?;
+        io::Write::write_all(&mut file, format!("{entry}\n").as_bytes())
+            .with_context(|| format!("Failed to write audit log: {}", path.display()))
+    }
+}
+
+/// Warnings and audit-mode blocks set aside so later enforced rules still get checked
+///
+/// Every set-aside result travels on the result the check returns (see
+/// [`HookResult::deferred`]), so [`Enforcement::enforce`] reports all of them.
+#[derive(Default)]
+struct SoftBlocks(Vec<HookResult>);
+
+impl SoftBlocks {
+    /// Set `result` aside if it is a warning or audit-mode block; otherwise return it,
+    /// carrying everything set aside so far, to be enforced now
+    fn defer(&mut self, config: &ConclaudeConfig, mut result: HookResult) -> Option<HookResult> {
+        if result.is_warning() || config.is_audit(result.mode.as_deref()) {
+            self.0.append(&mut result.deferred);
+            self.0.push(result);
+            None
+        } else {
+            result.deferred.append(&mut self.0);
+            Some(result)
+        }
+    }
+
+    /// Attach the set-aside results to `result`, or return them on their own
+    fn finish(mut self, result: Option<HookResult>) -> Option<HookResult> {
+        if let Some(mut result) = result {
+            result.deferred.append(&mut self.0);
+            return Some(result);
+        }
+        // The last result carries the earlier ones, which are enforced first
+        let mut last = self.0.pop()?;
+        last.deferred.append(&mut self.0);
+        Some(last)
+    }
 }
 
 /// Handles `PermissionRequest` hook events fired when Claude requests permission to execute a tool.
@@ -334,63 +757,164 @@ pub async fn handle_pre_tool_use() -> Result<HookResult> {
 /// Returns an error if payload validation fails or configuration loading fails.
 pub async fn handle_permission_request() -> Result<HookResult> {
     let payload: PermissionRequestPayload = read_payload_from_stdin()?;
+    process_permission_request(payload).await
+}
 
+/// Runs the `PermissionRequest` rules for an already-parsed payload.
+///
+/// Shared by the live hook and `conclaude explain`.
+///
+/// # Errors
+///
+/// Returns an error if payload validation fails, configuration loading fails, or a rule
+/// pattern is invalid.
+pub async fn process_permission_request(payload: PermissionRequestPayload) -> Result<HookResult> {
     validate_permission_request_payload(&payload).map_err(|e| anyhow::anyhow!(e))?;
 
-    println!(
+    eprintln!(
         "Processing PermissionRequest hook: session_id={}, tool_name={}",
         payload.base.session_id, payload.tool_name
     );
 
-    let (config, _config_path) = get_config().await?;
-
-    // If no permission_request config section exists, default to permissive mode (allow)
-    let Some(permission_config) = &config.permission_request else {
-        send_notification(
-            "PermissionRequest",
-            "success",
-            Some(&format!("Tool '{}' allowed (no config)", payload.tool_name)),
-        );
-        return Ok(HookResult::success());
+    let (config, config_path) = get_config().await?;
+    let context = &MatchContext {
+        cwd: Path::new(&payload.base.cwd),
+        project_root: get_config_dir(config_path),
+    };
+    let mut enforcement = Enforcement {
+        config,
+        config_dir: context.project_root,
+        hook: "PermissionRequest",
+        session_id: &payload.base.session_id,
+        tool_name: Some(&payload.tool_name),
+        warnings: Vec::new(),
     };
 
     // Check deny patterns first (deny takes precedence)
-    if let Some(deny_patterns) = &permission_config.deny {
-        for pattern_str in deny_patterns {
-            let pattern = Pattern::new(pattern_str)
-                .with_context(|| format!("Invalid glob pattern in deny list: {}", pattern_str))?;
-            if pattern.matches(&payload.tool_name) {
-                let message = format!(
-                    "Tool '{}' blocked by permissionRequest.deny pattern: {}",
-                    payload.tool_name, pattern_str
-                );
-                eprintln!(
-                    "PermissionRequest blocked by deny pattern: tool_name={}, pattern={}",
-                    payload.tool_name, pattern_str
-                );
+    if let Some(deny_patterns) = config
+        .permission_request
+        .as_ref()
+        .and_then(|c| c.deny.as_ref())
+    {
+        if let Some(pattern_str) =
+            find_permission_rule_match(deny_patterns, "deny", &payload, context, false)?
+        {
+            let message = format!(
+                "Tool '{}' blocked by permissionRequest.deny pattern: {}",
+                payload.tool_name, pattern_str
+            );
+            eprintln!(
+                "PermissionRequest blocked by deny pattern: tool_name={}, pattern={}",
+                payload.tool_name, pattern_str
+            );
+            let result =
+                HookResult::permission_request_decision(PermissionBehavior::Deny, Some(message));
+            if let Some(result) = enforcement.enforce("deny", result) {
                 send_notification(
                     "PermissionRequest",
                     "failure",
                     Some(&format!("Tool '{}' denied", payload.tool_name)),
                 );
-                return Ok(HookResult::blocked(message));
+                return Ok(result);
             }
         }
     }
 
-    // Check allow patterns second
-    if let Some(allow_patterns) = &permission_config.allow {
-        for pattern_str in allow_patterns {
-            let pattern = Pattern::new(pattern_str)
-                .with_context(|| format!("Invalid glob pattern in allow list: {}", pattern_str))?;
-            if pattern.matches(&payload.tool_name) {
-                send_notification(
-                    "PermissionRequest",
-                    "success",
-                    Some(&format!("Tool '{}' allowed", payload.tool_name)),
+    // MCP policies come next, so one section governs every MCP server. An MCP allow
+    // still yields to permissionRequest.ask below.
+    let mut mcp_allowed = false;
+    if let Some(decision) =
+        evaluate_mcp_policy(&config.mcp, &payload.tool_name, &payload.tool_input)?
+    {
+        eprintln!(
+            "PermissionRequest decided by MCP policy: tool_name={}, action={}, source={}",
+            payload.tool_name, decision.action, decision.source
+        );
+        trace::record(
+            decision.source.clone(),
+            format!("{} for {}", decision.action, payload.tool_name),
+            true,
+        );
+        match decision.action.as_str() {
+            "deny" => {
+                let result = HookResult::permission_request_decision(
+                    PermissionBehavior::Deny,
+                    Some(decision.reason(&payload.tool_name)),
                 );
-                return Ok(HookResult::success());
+                if let Some(result) = enforcement.enforce("deny", result) {
+                    send_notification(
+                        "PermissionRequest",
+                        "failure",
+                        Some(&format!("Tool '{}' denied", payload.tool_name)),
+                    );
+                    return Ok(result);
+                }
             }
+            "ask" => return Ok(HookResult::success()),
+            _ => mcp_allowed = true,
+        }
+    }
+
+    // Check ask patterns second: no decision is returned, so the normal permission
+    // dialog is shown to the user
+    if let Some(ask_patterns) = config
+        .permission_request
+        .as_ref()
+        .and_then(|c| c.ask.as_ref())
+    {
+        if let Some(pattern_str) =
+            find_permission_rule_match(ask_patterns, "ask", &payload, context, false)?
+        {
+            eprintln!(
+                "PermissionRequest deferred to user by ask pattern: tool_name={}, pattern={}",
+                payload.tool_name, pattern_str
+            );
+            send_notification(
+                "PermissionRequest",
+                "success",
+                Some(&format!(
+                    "Tool '{}' requires confirmation",
+                    payload.tool_name
+                )),
+            );
+            return Ok(HookResult::success());
+        }
+    }
+
+    if mcp_allowed {
+        send_notification(
+            "PermissionRequest",
+            "success",
+            Some(&format!("Tool '{}' allowed", payload.tool_name)),
+        );
+        return Ok(HookResult::permission_request_decision(
+            PermissionBehavior::Allow,
+            None,
+        ));
+    }
+
+    // If no permission_request config section exists, default to permissive mode (allow)
+    let Some(permission_config) = &config.permission_request else {
+        send_notification(
+            "PermissionRequest",
+            "success",
+            Some(&format!("Tool '{}' allowed (no config)", payload.tool_name)),
+        );
+        return Ok(HookResult::success());
+    };
+
+    // Check allow patterns third
+    if let Some(allow_patterns) = &permission_config.allow {
+        if find_permission_rule_match(allow_patterns, "allow", &payload, context, true)?.is_some() {
+            send_notification(
+                "PermissionRequest",
+                "success",
+                Some(&format!("Tool '{}' allowed", payload.tool_name)),
+            );
+            return Ok(HookResult::permission_request_decision(
+                PermissionBehavior::Allow,
+                None,
+            ));
         }
     }
 
@@ -402,6 +926,23 @@ pub async fn handle_permission_request() -> Result<HookResult> {
             "success",
             Some(&format!("Tool '{}' allowed by default", payload.tool_name)),
         );
+        Ok(HookResult::permission_request_decision(
+            PermissionBehavior::Allow,
+            None,
+        ))
+    } else if default_action == "ask" {
+        eprintln!(
+            "PermissionRequest deferred to user by default: tool_name={}",
+            payload.tool_name
+        );
+        send_notification(
+            "PermissionRequest",
+            "success",
+            Some(&format!(
+                "Tool '{}' requires confirmation by default",
+                payload.tool_name
+            )),
+        );
         Ok(HookResult::success())
     } else {
         // default is "deny"
@@ -413,12 +954,18 @@ pub async fn handle_permission_request() -> Result<HookResult> {
             "PermissionRequest blocked by default: tool_name={}",
             payload.tool_name
         );
+        let result =
+            HookResult::permission_request_decision(PermissionBehavior::Deny, Some(message));
+        let Some(result) = enforcement.enforce("deny", result) else {
+            // Audited: leave the decision to the user as if no rule had run
+            return Ok(HookResult::success());
+        };
         send_notification(
             "PermissionRequest",
             "failure",
             Some(&format!("Tool '{}' denied by default", payload.tool_name)),
         );
-        Ok(HookResult::blocked(message))
+        Ok(result)
     }
 }
 
@@ -428,16 +975,33 @@ pub async fn handle_permission_request() -> Result<HookResult> {
 ///
 /// Returns an error if configuration loading fails, directory access fails, or glob pattern processing fails.
 async fn check_file_validation_rules(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
-    let (config, config_path) = get_config().await?;
-
     // Extract file path from tool input
     let file_path = extract_file_path(&payload.tool_input);
     let Some(file_path) = file_path else {
         return Ok(None);
     };
 
+    check_file_path_rules(&payload.tool_name, &file_path, payload.tool_name == "Write").await
+}
+
+/// Check a single written file against `preventRootAdditions`, `uneditableFiles` and
+/// `preventAdditions`
+///
+/// `may_create` enables the checks that only apply when a new file can be created.
+///
+/// # Errors
+///
+/// Returns an error if configuration loading fails, directory access fails, or glob pattern processing fails.
+async fn check_file_path_rules(
+    tool_name: &str,
+    file_path: &str,
+    may_create: bool,
+) -> Result<Option<HookResult>> {
+    let (config, config_path) = get_config().await?;
+
     let cwd = std::env::current_dir().context("Failed to get current working directory")?;
-    let resolved_path = cwd.join(&file_path);
+    let mut soft_blocks = SoftBlocks::default();
+    let resolved_path = cwd.join(file_path);
     let relative_path = resolved_path
         .strip_prefix(&cwd)
         .unwrap_or(resolved_path.as_path())
@@ -447,80 +1011,471 @@ async fn check_file_validation_rules(payload: &PreToolUsePayload) -> Result<Opti
     // Check preventRootAdditions rule - only applies to Write tool for NEW files
     // File existence check allows modifications to existing root files (e.g., package.json)
     // but prevents creation of new files at root
-    if config.pre_tool_use.prevent_root_additions
-        && payload.tool_name == "Write"
-        && is_root_addition(&file_path, &relative_path, config_path)
-        && !resolved_path.exists()
-    {
+    let root_addition = config.pre_tool_use.prevent_root_additions
+        && may_create
+        && is_root_addition(file_path, &relative_path, config_path)
+        && !resolved_path.exists();
+    if config.pre_tool_use.prevent_root_additions {
+        trace::record(
+            "preToolUse.preventRootAdditions",
+            format!("new file at repository root: {file_path}"),
+            root_addition,
+        );
+    }
+    if root_addition {
         let error_message = format!(
             "Blocked {} operation: preToolUse.preventRootAdditions setting prevents creating files at repository root. File: {}",
-            payload.tool_name, file_path
+            tool_name, file_path
         );
 
         eprintln!(
             "PreToolUse blocked by preToolUse.preventRootAdditions setting: tool_name={}, file_path={}",
-            payload.tool_name, file_path
+            tool_name, file_path
         );
 
-        return Ok(Some(HookResult::blocked(error_message)));
+        if let Some(result) = soft_blocks.defer(config, HookResult::blocked(error_message)) {
+            return Ok(Some(result));
+        }
     }
 
     // Check uneditableFiles rule
-    for rule in &config.pre_tool_use.uneditable_files {
+    for (idx, rule) in config.pre_tool_use.uneditable_files.iter().enumerate() {
         let pattern = rule.pattern();
-        if matches_uneditable_pattern(
-            &file_path,
+        let matched = matches_uneditable_pattern(
+            file_path,
             &relative_path,
             &resolved_path.to_string_lossy(),
             pattern,
-        )? {
+        )?;
+        trace::record(
+            format!("preToolUse.uneditableFiles[{idx}]"),
+            format!("pattern '{pattern}' against {file_path}"),
+            matched,
+        );
+        if matched {
             // Use custom message if provided, otherwise use generic message
             let error_message = if let Some(custom_msg) = rule.message() {
                 custom_msg.to_string()
+            } else if rule.severity() == Some("warn") {
+                format!(
+                    "{tool_name} operation on a file matching preToolUse.uneditableFiles pattern '{pattern}'. File: {file_path}"
+                )
             } else {
                 format!(
                     "Blocked {} operation: file matches preToolUse.uneditableFiles pattern '{}'. File: {}",
-                    payload.tool_name, pattern, file_path
+                    tool_name, pattern, file_path
                 )
             };
 
             eprintln!(
                 "PreToolUse blocked by preToolUse.uneditableFiles pattern: tool_name={}, file_path={}, pattern={}",
-                payload.tool_name, file_path, pattern
+                tool_name, file_path, pattern
             );
 
-            return Ok(Some(HookResult::blocked(error_message)));
+            let result = HookResult::blocked(error_message)
+                .with_mode(rule.mode())
+                .with_severity(rule.severity());
+            if let Some(result) = soft_blocks.defer(config, result) {
+                return Ok(Some(result));
+            }
         }
     }
 
     // Check preventAdditions rule - only applies to Write tool creating NEW files
     // Existing files can be overwritten (preventAdditions only blocks new file creation)
-    if payload.tool_name == "Write" && !resolved_path.exists() {
-        for pattern in &config.pre_tool_use.prevent_additions {
-            if matches_uneditable_pattern(
-                &file_path,
+    if may_create && !resolved_path.exists() {
+        for (idx, pattern) in config.pre_tool_use.prevent_additions.iter().enumerate() {
+            let matched = matches_uneditable_pattern(
+                file_path,
                 &relative_path,
                 &resolved_path.to_string_lossy(),
                 pattern,
-            )? {
+            )?;
+            trace::record(
+                format!("preToolUse.preventAdditions[{idx}]"),
+                format!("pattern '{pattern}' against new file {file_path}"),
+                matched,
+            );
+            if matched {
                 let error_message = format!(
                     "Blocked {} operation: file matches preToolUse.preventAdditions pattern '{}'. File: {}",
-                    payload.tool_name, pattern, file_path
+                    tool_name, pattern, file_path
                 );
 
                 eprintln!(
                     "PreToolUse blocked by preToolUse.preventAdditions pattern: tool_name={}, file_path={}, pattern={}",
-                    payload.tool_name, file_path, pattern
+                    tool_name, file_path, pattern
                 );
 
-                return Ok(Some(HookResult::blocked(error_message)));
+                if let Some(result) = soft_blocks.defer(config, HookResult::blocked(error_message))
+                {
+                    return Ok(Some(result));
+                }
+            }
+        }
+    }
+
+    Ok(soft_blocks.finish(None))
+}
+
+/// Check the files a tool call reads against `preToolUse.unreadableFiles`
+///
+/// # Errors
+///
+/// Returns an error if configuration loading fails or a glob pattern is invalid.
+async fn check_unreadable_files(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
+    let (config, _config_path) = get_config().await?;
+    if config.pre_tool_use.unreadable_files.is_empty() {
+        return Ok(None);
+    }
+
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    let mut soft_blocks = SoftBlocks::default();
+    for file_path in collect_read_paths(&payload.tool_name, &payload.tool_input) {
+        if let Some(rule) =
+            find_unreadable_file_rule(&config.pre_tool_use.unreadable_files, &file_path, &cwd)?
+        {
+            let error_message = if let Some(custom_msg) = rule.message() {
+                custom_msg.to_string()
+            } else if rule.severity() == Some("warn") {
+                format!(
+                    "{} operation on a file matching preToolUse.unreadableFiles pattern '{}'. File: {}",
+                    payload.tool_name,
+                    rule.pattern(),
+                    file_path
+                )
+            } else {
+                format!(
+                    "Blocked {} operation: file matches preToolUse.unreadableFiles pattern '{}'. File: {}",
+                    payload.tool_name,
+                    rule.pattern(),
+                    file_path
+                )
+            };
+
+            eprintln!(
+                "PreToolUse blocked by preToolUse.unreadableFiles pattern: tool_name={}, file_path={}, pattern={}",
+                payload.tool_name,
+                file_path,
+                rule.pattern()
+            );
+
+            let result = HookResult::blocked(error_message)
+                .with_mode(rule.mode())
+                .with_severity(rule.severity());
+            if let Some(result) = soft_blocks.defer(config, result) {
+                return Ok(Some(result));
+            }
+        }
+    }
+
+    Ok(soft_blocks.finish(None))
+}
+
+/// Collect every path a tool call reads or writes
+///
+/// Combines the read paths from [`collect_read_paths`], the file edited by Write,
+/// Edit, `MultiEdit` and `NotebookEdit`, and the files written by Bash commands
+/// (including `mv`/`cp` sources).
+#[must_use]
+pub fn collect_accessed_paths<S: std::hash::BuildHasher>(
+    tool_name: &str,
+    tool_input: &HashMap<String, Value, S>,
+) -> Vec<String> {
+    let mut paths = collect_read_paths(tool_name, tool_input);
+    match tool_name {
+        "Write" | "Edit" | "MultiEdit" | "NotebookEdit" => {
+            paths.extend(extract_file_path(tool_input));
+        }
+        "Bash" => {
+            if let Some(command) = extract_bash_command(tool_input) {
+                for target in extract_write_targets(&command) {
+                    paths.extend(target.source);
+                    paths.push(target.path);
+                }
+            }
+        }
+        _ => {}
+    }
+    paths.dedup();
+    paths
+}
+
+/// Find the first path that resolves outside every allowed root
+///
+/// Paths are resolved with [`resolve_path`], so symlinks and `..` components cannot
+/// escape. Device paths under `/dev/` are always allowed. `roots` must already be
+/// resolved. Returns the original path and where it resolves to.
+#[must_use]
+pub fn find_path_outside_workspace(
+    paths: &[String],
+    cwd: &Path,
+    roots: &[std::path::PathBuf],
+) -> Option<(String, std::path::PathBuf)> {
+    paths
+        .iter()
+        .filter(|path| !path.starts_with("/dev/"))
+        .map(|path| (path.clone(), resolve_path(path, cwd)))
+        .find(|(_, resolved)| !roots.iter().any(|root| resolved.starts_with(root)))
+}
+
+/// Check `preToolUse.quotas` for this call and count it against the session
+///
+/// A Write counts as a new file when its target does not exist yet. Nothing is counted
+/// unless `record` is set.
+///
+/// # Errors
+///
+/// Returns an error if configuration loading fails or the quota counters cannot be updated.
+async fn check_session_quotas(
+    payload: &PreToolUsePayload,
+    record: bool,
+) -> Result<Option<HookResult>> {
+    let (config, _config_path) = get_config().await?;
+    let rules = &config.pre_tool_use.quotas;
+    if rules.is_empty() {
+        return Ok(None);
+    }
+
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    let new_file = payload.tool_name == "Write"
+        && extract_file_path(&payload.tool_input)
+            .is_some_and(|file_path| !resolve_path(&file_path, &cwd).exists());
+    let Some(exceeded) = quotas::check_and_record(
+        rules,
+        &payload.base.session_id,
+        &payload.tool_name,
+        new_file,
+        record,
+    )?
+    else {
+        return Ok(None);
+    };
+
+    eprintln!(
+        "PreToolUse blocked by preToolUse.quotas[{}]: tool_name={}, session_id={}",
+        exceeded.index, payload.tool_name, payload.base.session_id
+    );
+    Ok(Some(HookResult::blocked(exceeded.message)))
+}
+
+/// Block tool calls that read or write outside `preToolUse.workspaceBoundary`
+///
+/// # Errors
+///
+/// Returns an error if configuration loading fails or the working directory is unavailable.
+async fn check_workspace_boundary(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
+    let (config, config_path) = get_config().await?;
+    let boundary = &config.pre_tool_use.workspace_boundary;
+    if !boundary.enabled {
+        return Ok(None);
+    }
+
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    let project_root = resolve_path(&get_config_dir(config_path).to_string_lossy(), &cwd);
+    let mut roots = vec![project_root.clone()];
+    roots.extend(
+        boundary
+            .allowed_directories
+            .iter()
+            .map(|dir| resolve_path(dir, &project_root)),
+    );
+
+    let paths = collect_accessed_paths(&payload.tool_name, &payload.tool_input);
+    let outside = find_path_outside_workspace(&paths, &cwd, &roots);
+    trace::record(
+        "preToolUse.workspaceBoundary",
+        format!("paths {paths:?} inside {}", project_root.display()),
+        outside.is_some(),
+    );
+    let Some((path, resolved)) = outside else {
+        return Ok(None);
+    };
+
+    eprintln!(
+        "PreToolUse blocked by preToolUse.workspaceBoundary: tool_name={}, path={}, resolved={}",
+        payload.tool_name,
+        path,
+        resolved.display()
+    );
+    let message = boundary.message.clone().unwrap_or_else(|| {
+        format!(
+            "Blocked {} operation: '{path}' resolves to {}, outside the project root {}. Add the directory to preToolUse.workspaceBoundary.allowedDirectories to allow it.",
+            payload.tool_name,
+            resolved.display(),
+            project_root.display()
+        )
+    });
+    Ok(Some(HookResult::blocked(message)))
+}
+
+/// Collect the paths a tool call reads
+///
+/// `Read` and `NotebookRead` read one file. `Grep` and `Glob` search from `path` and
+/// filter with `glob`/`pattern`, so both the path and the filter (joined to the path)
+/// are returned. Bash commands contribute the files printed by `cat`-style commands.
+#[must_use]
+pub fn collect_read_paths<S: std::hash::BuildHasher>(
+    tool_name: &str,
+    tool_input: &HashMap<String, Value, S>,
+) -> Vec<String> {
+    let input = |key: &str| {
+        tool_input
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|s| !s.is_empty())
+    };
+
+    match tool_name {
+        "Read" | "NotebookRead" => extract_file_path(tool_input).into_iter().collect(),
+        "Grep" | "Glob" => {
+            let filter_key = if tool_name == "Grep" {
+                "glob"
+            } else {
+                "pattern"
+            };
+            let mut paths: Vec<String> = input("path").map(str::to_string).into_iter().collect();
+            if let Some(filter) = input(filter_key) {
+                paths.push(filter.to_string());
+                if let Some(base) = input("path") {
+                    paths.push(Path::new(base).join(filter).to_string_lossy().to_string());
+                }
+            }
+            paths
+        }
+        "Bash" => extract_bash_command(tool_input)
+            .map(|command| extract_read_targets(&command))
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Find the first `unreadableFiles` rule matching a path
+///
+/// Patterns starting with `~/` are expanded to the home directory. Paths are matched
+/// as given, relative to `cwd`, and resolved against `cwd`.
+///
+/// # Errors
+///
+/// Returns an error if a glob pattern is invalid.
+pub fn find_unreadable_file_rule<'a>(
+    rules: &'a [UnEditableFileRule],
+    file_path: &str,
+    cwd: &Path,
+) -> Result<Option<&'a UnEditableFileRule>> {
+    let expanded_path = match (file_path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => cwd.join(file_path),
+    };
+    let resolved_path = crate::permissions::normalize_path(&expanded_path);
+    let relative_path = resolved_path
+        .strip_prefix(cwd)
+        .unwrap_or(resolved_path.as_path())
+        .to_string_lossy()
+        .to_string();
+
+    for (idx, rule) in rules.iter().enumerate() {
+        let pattern = match (rule.pattern().strip_prefix("~/"), dirs::home_dir()) {
+            (Some(rest), Some(home)) => {
+                format!("{}/{rest}", Pattern::escape(&home.to_string_lossy()))
             }
+            _ => rule.pattern().to_string(),
+        };
+        let matched = matches_uneditable_pattern(
+            file_path,
+            &relative_path,
+            &resolved_path.to_string_lossy(),
+            &pattern,
+        )?;
+        trace::record(
+            format!("preToolUse.unreadableFiles[{idx}]"),
+            format!("pattern '{}' against {file_path}", rule.pattern()),
+            matched,
+        );
+        if matched {
+            return Ok(Some(rule));
         }
     }
 
     Ok(None)
 }
 
+/// Check files written by a Bash command against the file protection rules
+///
+/// # Errors
+///
+/// Returns an error if configuration loading fails or a protection check fails.
+async fn check_bash_write_targets(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
+    let Some(command) = extract_bash_command(&payload.tool_input) else {
+        return Ok(None);
+    };
+
+    let (config, _config_path) = get_config().await?;
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    let mut soft_blocks = SoftBlocks::default();
+    for target in extract_write_targets(&command) {
+        for file_path in resolve_write_target_paths(&target, &cwd) {
+            let results = [
+                check_git_ignored_path(&payload.tool_name, &file_path).await?,
+                check_file_path_rules(&payload.tool_name, &file_path, target.creates).await?,
+            ];
+            for result in results.into_iter().flatten() {
+                if let Some(result) = soft_blocks.defer(config, result) {
+                    return Ok(Some(result));
+                }
+            }
+        }
+    }
+
+    Ok(soft_blocks.finish(None))
+}
+
+/// Turn a Bash write target into the file paths it affects
+///
+/// Glob patterns such as `rm *.lock` are expanded against `cwd`, and `cp`/`mv`
+/// destinations that are directories resolve to the file created inside them.
+#[must_use]
+pub fn resolve_write_target_paths(target: &WriteTarget, cwd: &Path) -> Vec<String> {
+    if let Some(source) = &target.source {
+        let destination = cwd.join(&target.path);
+        if target.path.ends_with('/') || destination.is_dir() {
+            let name = Path::new(source)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            return vec![
+                Path::new(&target.path)
+                    .join(name)
+                    .to_string_lossy()
+                    .to_string(),
+            ];
+        }
+        return vec![target.path.clone()];
+    }
+
+    if target.path.contains(['*', '?', '[']) {
+        let pattern = cwd.join(&target.path).to_string_lossy().to_string();
+        let expanded: Vec<String> = glob::glob(&pattern)
+            .map(|paths| {
+                paths
+                    .filter_map(std::result::Result::ok)
+                    .map(|path| {
+                        path.strip_prefix(cwd)
+                            .unwrap_or(&path)
+                            .to_string_lossy()
+                            .to_string()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !expanded.is_empty() {
+            return expanded;
+        }
+    }
+
+    vec![target.path.clone()]
+}
+
 /// Extract file path from tool input
 pub fn extract_file_path<S: std::hash::BuildHasher>(
     tool_input: &std::collections::HashMap<String, Value, S>,
@@ -606,7 +1561,6 @@ pub fn matches_uneditable_pattern(
 /// # Errors
 ///
 /// Returns an error if payload validation fails or configuration loading fails.
-#[allow(clippy::unused_async)]
 pub async fn handle_post_tool_use() -> Result<HookResult> {
     let payload: PostToolUsePayload = read_payload_from_stdin()?;
 
@@ -616,11 +1570,36 @@ pub async fn handle_post_tool_use() -> Result<HookResult> {
         return Err(anyhow::anyhow!("Missing required field: tool_name"));
     }
 
-    println!(
+    eprintln!(
         "Processing PostToolUse hook: session_id={}, tool_name={}",
         payload.base.session_id, payload.tool_name
     );
 
+    let file_editing_tools = ["Write", "Edit", "MultiEdit"];
+    if file_editing_tools.contains(&payload.tool_name.as_str()) {
+        if let Some(file_path) = extract_file_path(&payload.tool_input) {
+            let (config, config_path) = get_config().await?;
+            let config_dir = get_config_dir(config_path);
+            let commands =
+                collect_post_tool_use_commands(&config.post_tool_use, &file_path, config_dir)?;
+
+            if !commands.is_empty() {
+                let env_vars = build_session_env_vars(&payload.base, "PostToolUse", config_dir);
+                if let Some(result) =
+                    execute_post_tool_use_commands(&commands, &file_path, &env_vars, config_dir)
+                        .await?
+                {
+                    send_notification(
+                        "PostToolUse",
+                        "failure",
+                        Some(&format!("Post-edit command failed for {file_path}")),
+                    );
+                    return Ok(result);
+                }
+            }
+        }
+    }
+
     // Send notification for post tool use completion
     send_notification(
         "PostToolUse",
@@ -630,95 +1609,816 @@ pub async fn handle_post_tool_use() -> Result<HookResult> {
     Ok(HookResult::success())
 }
 
-/// Handles `Notification` hook events when Claude sends system notifications.
+/// Collect `postToolUse.commands` whose glob matches the edited file
+///
+/// Patterns are checked in sorted order against the path as given and relative to
+/// `config_dir`.
 ///
 /// # Errors
 ///
-/// Returns an error if payload validation fails or configuration loading fails.
-#[allow(clippy::unused_async)]
-pub async fn handle_notification() -> Result<HookResult> {
-    let payload: NotificationPayload = read_payload_from_stdin()?;
+/// Returns an error if a glob pattern is invalid.
+pub fn collect_post_tool_use_commands<'a>(
+    config: &'a PostToolUseConfig,
+    file_path: &str,
+    config_dir: &Path,
+) -> Result<Vec<&'a PostToolUseCommand>> {
+    let relative_path = Path::new(file_path)
+        .strip_prefix(config_dir)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| file_path.to_string());
 
-    validate_base_payload(&payload.base).map_err(|e| anyhow::anyhow!(e))?;
+    let mut patterns: Vec<&String> = config.commands.keys().collect();
+    patterns.sort();
 
-    if payload.message.is_empty() {
-        return Err(anyhow::anyhow!("Missing required field: message"));
+    let mut commands = Vec::new();
+    for pattern_str in patterns {
+        let pattern = Pattern::new(pattern_str).with_context(|| {
+            format!("Invalid glob pattern in postToolUse config: {pattern_str}")
+        })?;
+        if pattern.matches(file_path) || pattern.matches(&relative_path) {
+            commands.extend(&config.commands[pattern_str]);
+        }
     }
 
-    println!(
-        "Processing Notification hook: session_id={}, message={}",
-        payload.base.session_id, payload.message
-    );
+    Ok(commands)
+}
 
-    // Send notification for notification hook processing
-    send_notification(
-        "Notification",
-        "success",
-        Some(&format!("Message: {}", payload.message)),
-    );
-    Ok(HookResult::success())
+/// Replace the `{file}` placeholder with the shell-quoted file path
+#[must_use]
+pub fn substitute_file_placeholder(command: &str, file_path: &str) -> String {
+    let quoted = format!("'{}'", file_path.replace('\'', r"'\''"));
+    command.replace("{file}", &quoted)
 }
 
-/// Handles `UserPromptSubmit` hook events when users submit input to Claude.
+/// Execute post tool use commands for an edited file
+///
+/// Stops at the first failing command and returns a blocking `decision` whose reason
+/// carries the truncated output, so Claude fixes the problem right away.
 ///
 /// # Errors
 ///
-/// Returns an error if payload validation fails or configuration loading fails.
-#[allow(clippy::unused_async)]
-pub async fn handle_user_prompt_submit() -> Result<HookResult> {
-    let payload: UserPromptSubmitPayload = read_payload_from_stdin()?;
+/// Returns an error if a command cannot be spawned.
+pub async fn execute_post_tool_use_commands(
+    commands: &[&PostToolUseCommand],
+    file_path: &str,
+    env_vars: &HashMap<String, String>,
+    config_dir: &Path,
+) -> Result<Option<HookResult>> {
+    eprintln!(
+        "Executing {} post tool use commands for {file_path}",
+        commands.len()
+    );
 
-    validate_base_payload(&payload.base).map_err(|e| anyhow::anyhow!(e))?;
+    for cmd_config in commands {
+        let command = substitute_file_placeholder(&cmd_config.run, file_path);
+        let heading = cmd_config
+            .message
+            .clone()
+            .unwrap_or_else(|| format!("Command `{command}` failed after editing {file_path}"));
+
+        let Some(output) =
+            capture_command_output(&command, config_dir, env_vars, cmd_config.timeout).await?
+        else {
+            let timeout_secs = cmd_config.timeout.unwrap_or_default();
+            return Ok(Some(HookResult::decision_block(format!(
+                "{heading}\nCommand timed out after {timeout_secs} seconds"
+            ))));
+        };
 
-    if payload.prompt.is_empty() {
-        return Err(anyhow::anyhow!("Missing required field: prompt"));
-    }
+        if output.status.success() {
+            continue;
+        }
 
-    println!(
-        "Processing UserPromptSubmit hook: session_id={}",
-        payload.base.session_id
-    );
+        let exit_code = output.status.code().unwrap_or(1);
+        eprintln!("Post tool use command failed with exit code {exit_code}: {command}");
 
-    // Send notification for user prompt submission
-    send_notification("UserPromptSubmit", "success", Some("User input received"));
-    Ok(HookResult::success())
+        let mut streams = Vec::new();
+        if cmd_config.show_stdout.unwrap_or(true) {
+            streams.push(String::from_utf8_lossy(&output.stdout).to_string());
+        }
+        if cmd_config.show_stderr.unwrap_or(true) {
+            streams.push(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+        let text = streams
+            .iter()
+            .map(|stream| stream.trim_end())
+            .filter(|stream| !stream.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let (truncated, is_truncated, omitted) =
+            truncate_output(&text, cmd_config.max_output_lines.unwrap_or(100));
+        let mut reason = format!("{heading} (exit code {exit_code})");
+        if !truncated.is_empty() {
+            reason.push('\n');
+            reason.push_str(&truncated);
+        }
+        if is_truncated {
+            reason.push_str(&format!("\n... ({omitted} lines omitted)"));
+        }
+
+        return Ok(Some(HookResult::decision_block(reason)));
+    }
+
+    Ok(None)
 }
 
-/// Handles `SessionStart` hook events when a new Claude session begins.
+/// Handles `Notification` hook events when Claude sends system notifications.
 ///
 /// # Errors
 ///
 /// Returns an error if payload validation fails or configuration loading fails.
-#[allow(clippy::unused_async)]
-pub async fn handle_session_start() -> Result<HookResult> {
-    let payload: SessionStartPayload = read_payload_from_stdin()?;
+pub async fn handle_notification() -> Result<HookResult> {
+    let payload: NotificationPayload = read_payload_from_stdin()?;
 
     validate_base_payload(&payload.base).map_err(|e| anyhow::anyhow!(e))?;
 
-    if payload.source.is_empty() {
-        return Err(anyhow::anyhow!("Missing required field: source"));
+    if payload.message.is_empty() {
+        return Err(anyhow::anyhow!("Missing required field: message"));
     }
 
-    println!(
-        "Processing SessionStart hook: session_id={}, source={}",
-        payload.base.session_id, payload.source
+    eprintln!(
+        "Processing Notification hook: session_id={}, message={}",
+        payload.base.session_id, payload.message
     );
 
-    // Send notification for session start
-    send_notification(
-        "SessionStart",
-        "success",
-        Some(&format!("Session started from {}", payload.source)),
-    );
-    Ok(HookResult::success())
-}
+    let (config, config_path) = get_config().await?;
+    let title = payload.title.as_deref().unwrap_or("");
+    let Some(route) =
+        select_notification_route(&config.notifications.routes, &payload.message, title)?
+    else {
+        // Unrouted notifications fall back to the desktop notification
+        send_notification(
+            "Notification",
+            "success",
+            Some(&format!("Message: {}", payload.message)),
+        );
+        return Ok(HookResult::success());
+    };
 
-/// Handles `SessionEnd` hook events when a Claude session terminates.
+    let config_dir = get_config_dir(config_path);
+    let mut env_vars = build_session_env_vars(&payload.base, "Notification", config_dir);
+    env_vars.insert(
+        "CONCLAUDE_NOTIFICATION_MESSAGE".to_string(),
+        payload.message.clone(),
+    );
+    env_vars.insert(
+        "CONCLAUDE_NOTIFICATION_TITLE".to_string(),
+        title.to_string(),
+    );
+
+    for sink in &route.sinks {
+        // Sink failures are logged but never fail the hook
+        if let Err(e) = deliver_notification(sink, &payload, config_dir, &env_vars).await {
+            eprintln!("Failed to deliver notification: {e}");
+        }
+    }
+
+    Ok(HookResult::success())
+}
+
+/// Default timeout for notification command sinks, in seconds
+const NOTIFICATION_COMMAND_TIMEOUT_SECS: u64 = 10;
+
+/// Timeout for notification HTTP sinks, in seconds
+const NOTIFICATION_HTTP_TIMEOUT_SECS: u64 = 5;
+
+/// Find the first notification route whose `message` and `title` regexes match
+///
+/// # Errors
+///
+/// Returns an error if a route contains an invalid regex.
+pub fn select_notification_route<'a>(
+    routes: &'a [NotificationRoute],
+    message: &str,
+    title: &str,
+) -> Result<Option<&'a NotificationRoute>> {
+    for route in routes {
+        let mut matched = true;
+        for (pattern, text) in [(&route.message, message), (&route.title, title)] {
+            if let Some(pattern) = pattern {
+                let regex = regex::Regex::new(pattern)
+                    .with_context(|| format!("Invalid notification route regex: {pattern}"))?;
+                matched &= regex.is_match(text);
+            }
+        }
+        if matched {
+            return Ok(Some(route));
+        }
+    }
+    Ok(None)
+}
+
+/// Deliver a notification to a single sink
+async fn deliver_notification(
+    sink: &NotificationSink,
+    payload: &NotificationPayload,
+    config_dir: &Path,
+    env_vars: &HashMap<String, String>,
+) -> Result<()> {
+    let title = payload.title.as_deref().unwrap_or("");
+
+    if let Some(command) = &sink.command {
+        if let Some(output) = capture_command_output(
+            command,
+            config_dir,
+            env_vars,
+            Some(NOTIFICATION_COMMAND_TIMEOUT_SECS),
+        )
+        .await?
+        {
+            if !output.status.success() {
+                return Err(anyhow::anyhow!(
+                    "Notification command failed with exit code {}: {}\n{}",
+                    output.status.code().unwrap_or(1),
+                    command,
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+            }
+        }
+    } else if let Some(file) = &sink.file {
+        append_notification_to_file(
+            &resolve_sink_path(file, config_dir),
+            title,
+            &payload.message,
+        )?;
+    } else if let Some(kind) = &sink.terminal {
+        let alert = format_terminal_alert(kind, title, &payload.message);
+        let mut tty = fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/tty")
+            .context("Failed to open controlling terminal")?;
+        io::Write::write_all(&mut tty, alert.as_bytes())
+            .context("Failed to write terminal alert")?;
+    } else if let Some(url) = &sink.http {
+        let body = serde_json::json!({
+            "sessionId": payload.base.session_id,
+            "cwd": payload.base.cwd,
+            "title": payload.title,
+            "message": payload.message,
+        });
+        post_notification_http(url, &body).await?;
+    }
+
+    Ok(())
+}
+
+/// Resolve a file sink path, expanding `~/` and anchoring relative paths at the config directory
+fn resolve_sink_path(file: &str, config_dir: &Path) -> std::path::PathBuf {
+    if let Some(rest) = file.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    }
+    config_dir.join(file)
+}
+
+/// Append a timestamped notification line to a file, creating parent directories as needed
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be created or written.
+pub fn append_notification_to_file(path: &Path, title: &str, message: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let line = if title.is_empty() {
+        format!("{timestamp} {}\n", message.replace('\n', " "))
+    } else {
+        format!("{timestamp} [{title}] {}\n", message.replace('\n', " "))
+    };
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open notification file: {}", path.display()))?;
+    io::Write::write_all(&mut file, line.as_bytes())
+        .with_context(|| format!("Failed to write notification file: {}", path.display()))
+}
+
+/// Build the escape sequence for a terminal alert
+///
+/// `bell` produces a plain BEL; `osc9` produces an OSC 9 desktop notification, which
+/// terminals such as iTerm2, WezTerm, kitty, and Windows Terminal surface even over SSH.
+/// Control characters are stripped so the message cannot terminate the sequence early.
+#[must_use]
+pub fn format_terminal_alert(kind: &str, title: &str, message: &str) -> String {
+    if kind == "bell" {
+        return "\x07".to_string();
+    }
+
+    let text = if title.is_empty() {
+        message.to_string()
+    } else {
+        format!("{title}: {message}")
+    };
+    let text: String = text
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    format!("\x1b]9;{text}\x07")
+}
+
+/// POST a JSON body to a local HTTP endpoint and return the response status code
+///
+/// # Errors
+///
+/// Returns an error if the URL is not local, the connection fails or times out, or the
+/// endpoint responds with a non-2xx status.
+pub async fn post_notification_http(url: &str, body: &Value) -> Result<u16> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let target = parse_local_http_url(url)?;
+    let body = serde_json::to_string(body)?;
+    let host_header = if target.host.contains(':') {
+        format!("[{}]:{}", target.host, target.port)
+    } else {
+        format!("{}:{}", target.host, target.port)
+    };
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {host_header}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        target.path,
+        body.len()
+    );
+
+    let exchange = async {
+        let mut stream = tokio::net::TcpStream::connect((target.host.as_str(), target.port))
+            .await
+            .with_context(|| format!("Failed to connect to {url}"))?;
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        Ok::<_, anyhow::Error>(response)
+    };
+    let response = timeout(
+        Duration::from_secs(NOTIFICATION_HTTP_TIMEOUT_SECS),
+        exchange,
+    )
+    .await
+    .map_err(|_| anyhow::anyhow!("Timed out posting notification to {url}"))??;
+
+    let response = String::from_utf8_lossy(&response);
+    let status = response
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| anyhow::anyhow!("Invalid HTTP response from {url}"))?;
+
+    if !(200..300).contains(&status) {
+        return Err(anyhow::anyhow!(
+            "Notification endpoint {url} returned HTTP {status}"
+        ));
+    }
+    Ok(status)
+}
+
+/// Handles `UserPromptSubmit` hook events when users submit input to Claude.
+///
+/// # Errors
+///
+/// Returns an error if payload validation fails or configuration loading fails.
+pub async fn handle_user_prompt_submit() -> Result<HookResult> {
+    let payload: UserPromptSubmitPayload = read_payload_from_stdin()?;
+
+    validate_base_payload(&payload.base).map_err(|e| anyhow::anyhow!(e))?;
+
+    if payload.prompt.is_empty() {
+        return Err(anyhow::anyhow!("Missing required field: prompt"));
+    }
+
+    eprintln!(
+        "Processing UserPromptSubmit hook: session_id={}",
+        payload.base.session_id
+    );
+
+    let (config, config_path) = get_config().await?;
+
+    let screening = evaluate_prompt_rules(
+        &config.user_prompt_submit.rules,
+        &payload.prompt,
+        &payload.base.cwd,
+    )?;
+    if let Some(reason) = screening.block_reason {
+        eprintln!(
+            "UserPromptSubmit blocked by userPromptSubmit.rules: session_id={}",
+            payload.base.session_id
+        );
+        send_notification(
+            "UserPromptSubmit",
+            "failure",
+            Some("Prompt blocked by rules"),
+        );
+        return Ok(HookResult::decision_block(reason));
+    }
+
+    let context = collect_prompt_context(
+        &config.user_prompt_submit.context,
+        &payload,
+        get_config_dir(config_path),
+    )
+    .await?;
+
+    // Send notification for user prompt submission
+    send_notification("UserPromptSubmit", "success", Some("User input received"));
+
+    let rewrite_note = screening.rewritten_prompt.map(|prompt| {
+        format!(
+            "The user's prompt was rewritten by project policy. Treat this as the prompt instead:\n{prompt}"
+        )
+    });
+    let additional_context = match (rewrite_note, context) {
+        (Some(note), Some(context)) => Some(format!("{note}\n\n{context}")),
+        (note, context) => note.or(context),
+    };
+
+    let mut result = HookResult::success();
+    if let Some(additional_context) = additional_context {
+        result = result.with_hook_specific_output(HookSpecificOutput::additional_context(
+            "UserPromptSubmit",
+            additional_context,
+        ));
+    }
+    if !screening.warnings.is_empty() {
+        result = result.with_system_message(screening.warnings.join("\n"));
+    }
+    Ok(result)
+}
+
+/// Outcome of evaluating `userPromptSubmit.rules` against a prompt
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptScreening {
+    /// Reason from the first matching `block` rule
+    pub block_reason: Option<String>,
+    /// Messages from matching `warn` rules
+    pub warnings: Vec<String>,
+    /// Prompt after all matching `rewrite` rules, when it changed
+    pub rewritten_prompt: Option<String>,
+}
+
+/// Evaluate `userPromptSubmit.rules` against a prompt
+///
+/// Rules run in order. The first matching `block` rule stops evaluation; `warn` rules
+/// collect messages; `rewrite` rules apply their regex replacement in turn. Default
+/// messages name the rule pattern, never the matched text, so secrets aren't echoed.
+///
+/// # Errors
+///
+/// Returns an error if a regex or glob pattern is invalid.
+pub fn evaluate_prompt_rules(
+    rules: &[PromptRule],
+    prompt: &str,
+    cwd: &str,
+) -> Result<PromptScreening> {
+    let case_insensitive = glob::MatchOptions {
+        case_sensitive: false,
+        ..glob::MatchOptions::new()
+    };
+
+    let mut screening = PromptScreening::default();
+    let mut current_prompt = prompt.to_string();
+
+    for rule in rules {
+        if let Some(cwd_pattern) = &rule.cwd {
+            let pattern = Pattern::new(cwd_pattern).with_context(|| {
+                format!("Invalid glob pattern in userPromptSubmit.rules cwd: {cwd_pattern}")
+            })?;
+            if !pattern.matches(cwd) {
+                continue;
+            }
+        }
+
+        let (matched, pattern_desc) = if let Some(regex_str) = &rule.regex {
+            let regex = regex::Regex::new(regex_str)
+                .with_context(|| format!("Invalid regex in userPromptSubmit.rules: {regex_str}"))?;
+
+            if rule.action == "rewrite" {
+                let replace = rule.replace.as_deref().unwrap_or_default();
+                let rewritten = regex.replace_all(&current_prompt, replace).to_string();
+                if rewritten != current_prompt {
+                    current_prompt = rewritten;
+                }
+                continue;
+            }
+
+            (regex.is_match(prompt), regex_str)
+        } else if let Some(glob_str) = &rule.glob {
+            let pattern = Pattern::new(glob_str).with_context(|| {
+                format!("Invalid glob pattern in userPromptSubmit.rules: {glob_str}")
+            })?;
+            (pattern.matches_with(prompt, case_insensitive), glob_str)
+        } else {
+            continue;
+        };
+
+        if !matched {
+            continue;
+        }
+
+        match rule.action.as_str() {
+            "block" => {
+                screening.block_reason = Some(rule.message.clone().unwrap_or_else(|| {
+                    format!("Prompt blocked by userPromptSubmit.rules pattern: {pattern_desc}")
+                }));
+                return Ok(screening);
+            }
+            "warn" => screening
+                .warnings
+                .push(rule.message.clone().unwrap_or_else(|| {
+                    format!("Prompt matched userPromptSubmit.rules pattern: {pattern_desc}")
+                })),
+            _ => {}
+        }
+    }
+
+    if current_prompt != prompt {
+        screening.rewritten_prompt = Some(current_prompt);
+    }
+
+    Ok(screening)
+}
+
+/// Collect the `userPromptSubmit.context` entries whose trigger matches the prompt
+///
+/// Commands run with the session environment variables from `build_session_env_vars`.
+/// Sources that fail (missing file, failing or timed-out command) are logged and
+/// skipped so a broken context entry never blocks the prompt. Returns `None` when
+/// nothing was collected.
+///
+/// # Errors
+///
+/// Returns an error if a trigger regex is invalid or a command cannot be spawned.
+pub async fn collect_prompt_context(
+    rules: &[PromptContextRule],
+    payload: &UserPromptSubmitPayload,
+    config_dir: &Path,
+) -> Result<Option<String>> {
+    let prompt = payload.prompt.as_str();
+    let mut sections = Vec::new();
+
+    for rule in rules {
+        if let Some(trigger) = &rule.trigger {
+            let regex = regex::Regex::new(trigger).with_context(|| {
+                format!("Invalid regex in userPromptSubmit.context trigger: {trigger}")
+            })?;
+            if !regex.is_match(prompt) {
+                continue;
+            }
+        }
+
+        let content = if let Some(text) = &rule.text {
+            Some(text.clone())
+        } else if let Some(file) = &rule.file {
+            match fs::read_to_string(config_dir.join(file)) {
+                Ok(content) => Some(content),
+                Err(e) => {
+                    eprintln!("Skipping userPromptSubmit context file '{file}': {e}");
+                    None
+                }
+            }
+        } else if let Some(command) = &rule.command {
+            let env_vars = build_session_env_vars(&payload.base, "UserPromptSubmit", config_dir);
+            match capture_command_output(command, config_dir, &env_vars, rule.timeout).await? {
+                Some(output) if output.status.success() => {
+                    Some(String::from_utf8_lossy(&output.stdout).to_string())
+                }
+                Some(output) => {
+                    eprintln!(
+                        "Skipping userPromptSubmit context command '{command}': exit code {}",
+                        output.status.code().unwrap_or(1)
+                    );
+                    None
+                }
+                None => None,
+            }
+        } else {
+            None
+        };
+
+        let Some(content) = content.filter(|c| !c.trim().is_empty()) else {
+            continue;
+        };
+
+        let content = match rule.max_output_lines {
+            Some(max_lines) => {
+                let (truncated, is_truncated, omitted) = truncate_output(&content, max_lines);
+                if is_truncated {
+                    format!("{truncated}\n... ({omitted} lines omitted)")
+                } else {
+                    truncated
+                }
+            }
+            None => content,
+        };
+
+        sections.push(content.trim_end().to_string());
+    }
+
+    if sections.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(sections.join("\n\n")))
+    }
+}
+
+/// Run a shell command from the config directory and capture its output
+///
+/// Returns `None` (after logging) when the command times out.
+///
+/// # Errors
+///
+/// Returns an error if the command cannot be spawned or awaited.
+async fn capture_command_output(
+    command: &str,
+    config_dir: &Path,
+    env_vars: &HashMap<String, String>,
+    timeout_secs: Option<u64>,
+) -> Result<Option<std::process::Output>> {
+    let child = TokioCommand::new("bash")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .envs(env_vars)
+        .current_dir(config_dir)
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to spawn command: {command}"))?;
+
+    let output = if let Some(timeout_secs) = timeout_secs {
+        match timeout(Duration::from_secs(timeout_secs), child.wait_with_output()).await {
+            Ok(result) => {
+                result.with_context(|| format!("Failed to wait for command: {command}"))?
+            }
+            Err(_) => {
+                eprintln!("Command timed out after {timeout_secs} seconds: {command}");
+                return Ok(None);
+            }
+        }
+    } else {
+        child
+            .wait_with_output()
+            .await
+            .with_context(|| format!("Failed to wait for command: {command}"))?
+    };
+
+    Ok(Some(output))
+}
+
+/// Handles `SessionStart` hook events when a new Claude session begins.
+///
+/// # Errors
+///
+/// Returns an error if payload validation fails or configuration loading fails.
+pub async fn handle_session_start() -> Result<HookResult> {
+    let payload: SessionStartPayload = read_payload_from_stdin()?;
+
+    validate_base_payload(&payload.base).map_err(|e| anyhow::anyhow!(e))?;
+
+    if payload.source.is_empty() {
+        return Err(anyhow::anyhow!("Missing required field: source"));
+    }
+
+    eprintln!(
+        "Processing SessionStart hook: session_id={}, source={}",
+        payload.base.session_id, payload.source
+    );
+
+    let (config, config_path) = get_config().await?;
+    let config_dir = get_config_dir(config_path);
+    let commands = collect_session_start_commands(&config.session_start, &payload.source);
+
+    let context = if commands.is_empty() {
+        None
+    } else {
+        let mut env_vars = build_session_env_vars(&payload.base, "SessionStart", config_dir);
+        env_vars.insert(
+            "CONCLAUDE_SESSION_SOURCE".to_string(),
+            payload.source.clone(),
+        );
+        execute_session_start_commands(commands, &env_vars, config_dir).await?
+    };
+
+    // Send notification for session start
+    send_notification(
+        "SessionStart",
+        "success",
+        Some(&format!("Session started from {}", payload.source)),
+    );
+
+    match context {
+        Some(context) => Ok(HookResult::success().with_hook_specific_output(
+            HookSpecificOutput::additional_context("SessionStart", context),
+        )),
+        None => Ok(HookResult::success()),
+    }
+}
+
+/// Collect session start commands for a source, wildcard ("*") commands first
+#[must_use]
+pub fn collect_session_start_commands<'a>(
+    config: &'a SessionStartConfig,
+    source: &str,
+) -> Vec<&'a SessionStartCommand> {
+    ["*", source]
+        .iter()
+        .filter_map(|key| config.commands.get(*key))
+        .flatten()
+        .collect()
+}
+
+/// Execute session start commands and build the context injected into the session
+///
+/// Commands never block the session: failures are reported in the context with their
+/// exit code, and timed-out commands are skipped. Returns `None` when no command
+/// produced visible output.
+///
+/// # Errors
+///
+/// Returns an error if a command cannot be spawned.
+pub async fn execute_session_start_commands(
+    commands: Vec<&SessionStartCommand>,
+    env_vars: &HashMap<String, String>,
+    config_dir: &Path,
+) -> Result<Option<String>> {
+    eprintln!("Executing {} session start commands", commands.len());
+
+    let mut sections = Vec::new();
+
+    for cmd_config in commands {
+        let Some(output) =
+            capture_command_output(&cmd_config.run, config_dir, env_vars, cmd_config.timeout)
+                .await?
+        else {
+            continue;
+        };
+
+        let mut streams = Vec::new();
+        if cmd_config.show_stdout.unwrap_or(true) {
+            streams.push(String::from_utf8_lossy(&output.stdout).to_string());
+        }
+        if cmd_config.show_stderr.unwrap_or(false) {
+            streams.push(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+        let text = streams
+            .iter()
+            .map(|stream| stream.trim_end())
+            .filter(|stream| !stream.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut section = cmd_config
+            .message
+            .clone()
+            .unwrap_or_else(|| format!("$ {}", cmd_config.run));
+        if !output.status.success() {
+            let exit_code = output.status.code().unwrap_or(1);
+            eprintln!(
+                "Session start command failed with exit code {exit_code}: {}",
+                cmd_config.run
+            );
+            section.push_str(&format!(" (exit code {exit_code})"));
+        } else if text.is_empty() {
+            continue;
+        }
+
+        if !text.is_empty() {
+            let text = match cmd_config.max_output_lines {
+                Some(max_lines) => {
+                    let (truncated, is_truncated, omitted) = truncate_output(&text, max_lines);
+                    if is_truncated {
+                        format!("{truncated}\n... ({omitted} lines omitted)")
+                    } else {
+                        truncated
+                    }
+                }
+                None => text,
+            };
+            section.push('\n');
+            section.push_str(&text);
+        }
+
+        sections.push(section);
+    }
+
+    if sections.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(sections.join("\n\n")))
+    }
+}
+
+/// Handles `SessionEnd` hook events when a Claude session terminates.
 ///
 /// # Errors
 ///
 /// Returns an error if payload validation fails or configuration loading fails.
-#[allow(clippy::unused_async)]
 pub async fn handle_session_end() -> Result<HookResult> {
     let payload: SessionEndPayload = read_payload_from_stdin()?;
 
@@ -728,14 +2428,66 @@ pub async fn handle_session_end() -> Result<HookResult> {
         return Err(anyhow::anyhow!("Missing required field: reason"));
     }
 
-    println!(
+    eprintln!(
         "Processing SessionEnd hook: session_id={}, reason={}",
         payload.base.session_id, payload.reason
     );
 
+    // Quota counters are only needed while the session runs
+    if let Err(e) = quotas::clear_session(&payload.base.session_id) {
+        eprintln!("Failed to clear session quotas: {e}");
+    }
+
+    let (config, config_path) = get_config().await?;
+    let config_dir = get_config_dir(config_path);
+    let commands = collect_session_end_commands(&config.session_end, &payload.reason);
+
+    if !commands.is_empty() {
+        let mut env_vars = build_session_env_vars(&payload.base, "SessionEnd", config_dir);
+        env_vars.insert(
+            "CONCLAUDE_SESSION_END_REASON".to_string(),
+            payload.reason.clone(),
+        );
+
+        eprintln!("Executing {} session end commands", commands.len());
+        for command in commands {
+            // Cleanup failures are logged but never fail the hook
+            if let Some(output) =
+                capture_command_output(&command.run, config_dir, &env_vars, command.timeout).await?
+            {
+                if !output.status.success() {
+                    eprintln!(
+                        "Session end command failed with exit code {}: {}\n{}",
+                        output.status.code().unwrap_or(1),
+                        command.run,
+                        String::from_utf8_lossy(&output.stderr)
+                    );
+                }
+            }
+        }
+    }
+
     Ok(HookResult::success())
 }
 
+/// Collect session end commands that apply to an end reason
+#[must_use]
+pub fn collect_session_end_commands<'a>(
+    config: &'a SessionEndConfig,
+    reason: &str,
+) -> Vec<&'a SessionEndCommand> {
+    config
+        .commands
+        .iter()
+        .filter(|command| {
+            command
+                .reasons
+                .as_ref()
+                .is_none_or(|reasons| reasons.iter().any(|r| r == reason))
+        })
+        .collect()
+}
+
 /// Truncate output to a maximum number of lines
 ///
 /// Returns a tuple of (truncated_output, is_truncated, omitted_line_count)
@@ -793,11 +2545,12 @@ fn collect_stop_commands(config: &ConclaudeConfig) -> Result<Vec<StopCommandConf
 async fn execute_stop_commands(
     commands: &[StopCommandConfig],
     config_dir: &Path,
+    enforcement: &mut Enforcement<'_>,
 ) -> Result<Option<HookResult>> {
-    println!("Executing {} stop hook commands", commands.len());
+    eprintln!("Executing {} stop hook commands", commands.len());
 
     for (index, cmd_config) in commands.iter().enumerate() {
-        println!(
+        eprintln!(
             "Executing command {}/{}: {}",
             index + 1,
             commands.len(),
@@ -832,7 +2585,11 @@ async fn execute_stop_commands(
                     eprintln!("{}", error_msg);
 
                     let message = cmd_config.message.as_deref().unwrap_or(&error_msg);
-                    return Ok(Some(HookResult::blocked(message)));
+                    if let Some(result) = enforcement.enforce("block", HookResult::blocked(message))
+                    {
+                        return Ok(Some(result));
+                    }
+                    continue;
                 }
             }
         } else {
@@ -927,13 +2684,15 @@ async fn execute_stop_commands(
                 )
             };
 
-            return Ok(Some(HookResult::blocked(error_message)));
+            if let Some(result) = enforcement.enforce("block", HookResult::blocked(error_message)) {
+                return Ok(Some(result));
+            }
         }
 
         // Successful individual commands produce no output
     }
 
-    println!("All stop hook commands completed successfully");
+    eprintln!("All stop hook commands completed successfully");
     Ok(None)
 }
 
@@ -948,13 +2707,21 @@ pub async fn handle_stop() -> Result<HookResult> {
 
     validate_base_payload(&payload.base).map_err(|e| anyhow::anyhow!(e))?;
 
-    println!(
+    eprintln!(
         "Processing Stop hook: session_id={}",
         payload.base.session_id
     );
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut enforcement = Enforcement {
+        config,
+        config_dir,
+        hook: "Stop",
+        session_id: &payload.base.session_id,
+        tool_name: None,
+        warnings: Vec::new(),
+    };
 
     // Snapshot root directory if preventRootAdditions is enabled
     let root_snapshot = if config.pre_tool_use.prevent_root_additions {
@@ -967,7 +2734,9 @@ pub async fn handle_stop() -> Result<HookResult> {
     let commands_with_messages = collect_stop_commands(config)?;
 
     // Execute commands
-    if let Some(result) = execute_stop_commands(&commands_with_messages, config_dir).await? {
+    if let Some(result) =
+        execute_stop_commands(&commands_with_messages, config_dir, &mut enforcement).await?
+    {
         // Send notification for blocked/failed stop hook
         send_notification(
             "Stop",
@@ -984,7 +2753,9 @@ pub async fn handle_stop() -> Result<HookResult> {
 
     // Check root additions if enabled
     if let Some(snapshot) = root_snapshot {
-        if let Some(result) = check_root_additions(&snapshot)? {
+        if let Some(result) =
+            check_root_additions(&snapshot)?.and_then(|result| enforcement.enforce("block", result))
+        {
             // Send notification for blocked root additions
             send_notification(
                 "Stop",
@@ -1008,7 +2779,7 @@ pub async fn handle_stop() -> Result<HookResult> {
             .as_deref()
             .unwrap_or("continue working on the task");
 
-        println!("Infinite mode enabled, sending continuation message: {infinite_message}");
+        eprintln!("Infinite mode enabled, sending continuation message: {infinite_message}");
         // Send notification for infinite mode continuation
         send_notification(
             "Stop",
@@ -1035,7 +2806,7 @@ pub async fn handle_subagent_start() -> Result<HookResult> {
     // Validate the payload including agent_id, subagent_type, and agent_transcript_path fields
     validate_subagent_start_payload(&payload).map_err(|e| anyhow::anyhow!(e))?;
 
-    println!(
+    eprintln!(
         "Processing SubagentStart hook: session_id={}, agent_id={}",
         payload.base.session_id, payload.agent_id
     );
@@ -1121,7 +2892,7 @@ fn build_subagent_env_vars(
     payload: &SubagentStopPayload,
     config_dir: &Path,
 ) -> HashMap<String, String> {
-    let mut env_vars = HashMap::new();
+    let mut env_vars = build_session_env_vars(&payload.base, "SubagentStop", config_dir);
 
     // Agent-specific environment variables
     env_vars.insert("CONCLAUDE_AGENT_ID".to_string(), payload.agent_id.clone());
@@ -1130,20 +2901,28 @@ fn build_subagent_env_vars(
         payload.agent_transcript_path.clone(),
     );
 
-    // Session-level environment variables
-    env_vars.insert(
-        "CONCLAUDE_SESSION_ID".to_string(),
-        payload.base.session_id.clone(),
-    );
+    env_vars
+}
+
+/// Build the session-level environment variables shared by hook commands
+///
+/// Sets `CONCLAUDE_SESSION_ID`, `CONCLAUDE_TRANSCRIPT_PATH`, `CONCLAUDE_HOOK_EVENT`,
+/// `CONCLAUDE_CWD`, and `CONCLAUDE_CONFIG_DIR`.
+#[must_use]
+fn build_session_env_vars(
+    base: &BasePayload,
+    hook_event: &str,
+    config_dir: &Path,
+) -> HashMap<String, String> {
+    let mut env_vars = HashMap::new();
+
+    env_vars.insert("CONCLAUDE_SESSION_ID".to_string(), base.session_id.clone());
     env_vars.insert(
         "CONCLAUDE_TRANSCRIPT_PATH".to_string(),
-        payload.base.transcript_path.clone(),
-    );
-    env_vars.insert(
-        "CONCLAUDE_HOOK_EVENT".to_string(),
-        "SubagentStop".to_string(),
+        base.transcript_path.clone(),
     );
-    env_vars.insert("CONCLAUDE_CWD".to_string(), payload.base.cwd.clone());
+    env_vars.insert("CONCLAUDE_HOOK_EVENT".to_string(), hook_event.to_string());
+    env_vars.insert("CONCLAUDE_CWD".to_string(), base.cwd.clone());
     env_vars.insert(
         "CONCLAUDE_CONFIG_DIR".to_string(),
         config_dir.to_string_lossy().to_string(),
@@ -1203,10 +2982,10 @@ async fn execute_subagent_stop_commands(
         return Ok(());
     }
 
-    println!("Executing {} subagent stop hook commands", commands.len());
+    eprintln!("Executing {} subagent stop hook commands", commands.len());
 
     for (index, cmd_config) in commands.iter().enumerate() {
-        println!(
+        eprintln!(
             "Executing subagent stop command {}/{}: {}",
             index + 1,
             commands.len(),
@@ -1348,7 +3127,7 @@ async fn execute_subagent_stop_commands(
             } else {
                 stdout.to_string()
             };
-            println!("Stdout: {}", output_to_show);
+            eprintln!("Stdout: {}", output_to_show);
         }
 
         if cmd_config.show_stderr && !stderr.trim().is_empty() {
@@ -1366,7 +3145,7 @@ async fn execute_subagent_stop_commands(
         }
     }
 
-    println!("All subagent stop hook commands completed");
+    eprintln!("All subagent stop hook commands completed");
     Ok(())
 }
 
@@ -1388,7 +3167,7 @@ pub async fn handle_subagent_stop() -> Result<HookResult> {
     // Validate the payload including agent_id and agent_transcript_path fields
     validate_subagent_stop_payload(&payload).map_err(|e| anyhow::anyhow!(e))?;
 
-    println!(
+    eprintln!(
         "Processing SubagentStop hook: session_id={}, agent_id={}",
         payload.base.session_id, payload.agent_id
     );
@@ -1410,7 +3189,7 @@ pub async fn handle_subagent_stop() -> Result<HookResult> {
         let matching_patterns = match_subagent_patterns(&payload.agent_id, &config.subagent_stop)?;
 
         if !matching_patterns.is_empty() {
-            println!(
+            eprintln!(
                 "Agent '{}' matched patterns: {:?}",
                 payload.agent_id, matching_patterns
             );
@@ -1427,7 +3206,7 @@ pub async fn handle_subagent_stop() -> Result<HookResult> {
                 execute_subagent_stop_commands(&commands, &env_vars, config_dir).await?;
             }
         } else {
-            println!(
+            eprintln!(
                 "Agent '{}' did not match any configured patterns",
                 payload.agent_id
             );
@@ -1448,17 +3227,63 @@ pub async fn handle_subagent_stop() -> Result<HookResult> {
 /// # Errors
 ///
 /// Returns an error if payload validation fails or configuration loading fails.
-#[allow(clippy::unused_async)]
 pub async fn handle_pre_compact() -> Result<HookResult> {
     let payload: PreCompactPayload = read_payload_from_stdin()?;
 
     validate_base_payload(&payload.base).map_err(|e| anyhow::anyhow!(e))?;
 
-    println!(
+    eprintln!(
         "Processing PreCompact hook: session_id={}, trigger={:?}",
         payload.base.session_id, payload.trigger
     );
 
+    let (config, config_path) = get_config().await?;
+    let config_dir = get_config_dir(config_path);
+    let trigger = match payload.trigger {
+        CompactTrigger::Manual => "manual",
+        CompactTrigger::Auto => "auto",
+    };
+
+    let mut env_vars = build_session_env_vars(&payload.base, "PreCompact", config_dir);
+    env_vars.insert("CONCLAUDE_COMPACT_TRIGGER".to_string(), trigger.to_string());
+
+    if let Some(archive) = &config.pre_compact.archive {
+        // Archival failures are logged but never block compaction
+        match archive_transcript(
+            Path::new(&payload.base.transcript_path),
+            &config_dir.join(&archive.directory),
+            &payload.base.session_id,
+            trigger,
+            archive.gzip,
+            archive.max_archives,
+        ) {
+            Ok(archive_path) => {
+                eprintln!("Archived transcript to {}", archive_path.display());
+                env_vars.insert(
+                    "CONCLAUDE_ARCHIVE_PATH".to_string(),
+                    archive_path.to_string_lossy().to_string(),
+                );
+            }
+            Err(e) => eprintln!("Failed to archive transcript: {e:#}"),
+        }
+    }
+
+    for command in &config.pre_compact.commands {
+        eprintln!("Executing pre-compact command: {}", command.run);
+        if let Some(output) =
+            capture_command_output(&command.run, config_dir, &env_vars, command.timeout).await?
+        {
+            if !output.status.success() {
+                eprintln!(
+                    "Pre-compact command failed with exit code {}: {}\n{}",
+                    output.status.code().unwrap_or(1),
+                    command.run,
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+        }
+    }
+
     // Send notification for pre-compact hook
     send_notification(
         "PreCompact",
@@ -1468,129 +3293,580 @@ pub async fn handle_pre_compact() -> Result<HookResult> {
     Ok(HookResult::success())
 }
 
+/// Copy a transcript into `archive_dir` and rotate old archives
+///
+/// Archives are named `<UTC timestamp>-<session id>-<trigger>.jsonl`, with a `.gz`
+/// suffix when `gzip` is set, so name order is chronological. When `max_archives` is
+/// set, the oldest archives beyond that count are removed.
+///
+/// # Errors
+///
+/// Returns an error if the transcript cannot be read or the archive cannot be written.
+pub fn archive_transcript(
+    transcript_path: &Path,
+    archive_dir: &Path,
+    session_id: &str,
+    trigger: &str,
+    gzip: bool,
+    max_archives: Option<usize>,
+) -> Result<std::path::PathBuf> {
+    fs::create_dir_all(archive_dir).with_context(|| {
+        format!(
+            "Failed to create archive directory: {}",
+            archive_dir.display()
+        )
+    })?;
+
+    let safe_session_id: String = session_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
+    let extension = if gzip { "jsonl.gz" } else { "jsonl" };
+    let archive_path = archive_dir.join(format!(
+        "{timestamp}-{safe_session_id}-{trigger}.{extension}"
+    ));
+
+    let mut transcript = fs::File::open(transcript_path)
+        .with_context(|| format!("Failed to open transcript: {}", transcript_path.display()))?;
+    let archive_file = fs::File::create(&archive_path)
+        .with_context(|| format!("Failed to create archive: {}", archive_path.display()))?;
+
+    if gzip {
+        let mut encoder =
+            flate2::write::GzEncoder::new(archive_file, flate2::Compression::default());
+        io::copy(&mut transcript, &mut encoder)?;
+        encoder.finish()?;
+    } else {
+        let mut archive_file = archive_file;
+        io::copy(&mut transcript, &mut archive_file)?;
+    }
+
+    if let Some(max_archives) = max_archives {
+        rotate_archives(archive_dir, max_archives)?;
+    }
+
+    Ok(archive_path)
+}
+
+/// Remove the oldest transcript archives so at most `max_archives` remain
+///
+/// # Errors
+///
+/// Returns an error if the archive directory cannot be read or a file cannot be removed.
+fn rotate_archives(archive_dir: &Path, max_archives: usize) -> Result<()> {
+    let mut archives: Vec<std::path::PathBuf> = fs::read_dir(archive_dir)?
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.ends_with(".jsonl") || name.ends_with(".jsonl.gz"))
+        })
+        .collect();
+
+    if archives.len() <= max_archives {
+        return Ok(());
+    }
+
+    archives.sort();
+    let excess = archives.len() - max_archives;
+    for old_archive in &archives[..excess] {
+        fs::remove_file(old_archive)
+            .with_context(|| format!("Failed to remove old archive: {}", old_archive.display()))?;
+    }
+
+    Ok(())
+}
+
 /// Check tool usage validation rules
 ///
+/// Rules with `action: block` (or an unmatched `action: allow`) deny the tool call
+/// immediately. Rules with `action: ask` hand the decision back to the user, but a
+/// later blocking rule still takes precedence over a pending ask.
+///
 /// # Errors
 ///
 /// Returns an error if configuration loading fails or glob pattern creation fails.
 async fn check_tool_usage_rules(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
     let (config, _config_path) = get_config().await?;
 
-    for rule in &config.pre_tool_use.tool_usage_validation {
-        if rule.tool == payload.tool_name || rule.tool == "*" {
-            // Check if this is a Bash command with a commandPattern rule
-            if payload.tool_name == "Bash" && rule.command_pattern.is_some() {
-                // Extract the command
-                if let Some(command) = extract_bash_command(&payload.tool_input) {
-                    let pattern = rule.command_pattern.as_ref().unwrap();
-                    let mode = rule.match_mode.as_deref().unwrap_or("full");
-
-                    // Perform pattern matching based on mode
-                    let matches = if mode == "prefix" {
-                        // Prefix mode: test progressively longer prefixes
-                        let glob = Pattern::new(pattern)?;
-                        let words: Vec<&str> = command.split_whitespace().collect();
-                        (1..=words.len()).any(|i| {
-                            let prefix = words[..i].join(" ");
-                            glob.matches(&prefix)
-                        })
-                    } else {
-                        // Full mode: match entire command
-                        Pattern::new(pattern)?.matches(&command)
-                    };
+    let mut pending_ask: Option<HookResult> = None;
+    let mut soft_blocks = SoftBlocks::default();
 
-                    // Handle actions based on match result
-                    if rule.action == "block" && matches {
-                        let message = rule.message.clone().unwrap_or_else(|| {
+    // MCP policies: deny blocks, ask defers to the user; allow leaves the decision to
+    // the rules below and to PermissionRequest
+    if let Some(decision) =
+        evaluate_mcp_policy(&config.mcp, &payload.tool_name, &payload.tool_input)?
+    {
+        let reason = decision.reason(&payload.tool_name);
+        trace::record(
+            decision.source.clone(),
+            format!("{} for {}", decision.action, payload.tool_name),
+            true,
+        );
+        match decision.action.as_str() {
+            "deny" => {
+                if let Some(result) = soft_blocks.defer(config, HookResult::blocked(reason)) {
+                    return Ok(Some(result));
+                }
+            }
+            "ask" => pending_ask = Some(ask_tool_use(reason)),
+            _ => {}
+        }
+    }
+
+    for (idx, rule) in config.pre_tool_use.tool_usage_validation.iter().enumerate() {
+        let rule_name = format!("preToolUse.toolUsageValidation[{idx}]");
+        let tool_matches = rule.tool == "*" || tool_name_matches(&rule.tool, &payload.tool_name)?;
+        if !tool_matches {
+            trace::record(
+                rule_name,
+                format!("tool '{}' ({})", rule.tool, rule.action),
+                false,
+            );
+            continue;
+        }
+        let warn = rule.severity.as_deref() == Some("warn");
+        // Check if this is a Bash command with a commandPattern rule
+        if let Some(pattern) = rule
+            .command_pattern
+            .as_ref()
+            .filter(|_| payload.tool_name == "Bash")
+        {
+            // Extract the command
+            if let Some(command) = extract_bash_command(&payload.tool_input) {
+                let mode = rule.match_mode.as_deref().unwrap_or("full");
+                let command_match = match_bash_command_pattern(pattern, mode, &command)?;
+                // Block and ask rules fire if any part of the command matches, while
+                // allow rules require every simple command to match
+                let matches = if rule.action == "allow" {
+                    command_match.all
+                } else {
+                    command_match.any
+                };
+                trace::record(
+                    rule_name,
+                    format!("{} commandPattern '{pattern}' ({})", rule.tool, rule.action),
+                    matches,
+                );
+
+                // Handle actions based on match result
+                if rule.action == "block" && matches {
+                    let message = rule.message.clone().unwrap_or_else(|| {
+                        if warn {
+                            format!(
+                                "Bash command matches preToolUse.toolUsageValidation rule: {pattern}"
+                            )
+                        } else {
                             format!(
                                 "Bash command blocked by preToolUse.toolUsageValidation rule: {}",
                                 pattern
                             )
-                        });
-                        return Ok(Some(HookResult::blocked(message)));
-                    } else if rule.action == "allow" && !matches {
+                        }
+                    });
+                    let result = HookResult::blocked(message)
+                        .with_mode(rule.mode.as_deref())
+                        .with_severity(rule.severity.as_deref());
+                    if let Some(result) = soft_blocks.defer(config, result) {
+                        return Ok(Some(result));
+                    }
+                } else if rule.action == "ask" && matches {
+                    if pending_ask.is_none() {
                         let message = rule.message.clone().unwrap_or_else(|| {
                             format!(
-                                "Bash command blocked: does not match preToolUse.toolUsageValidation allow rule pattern: {}",
+                                "Bash command requires confirmation by preToolUse.toolUsageValidation rule: {}",
                                 pattern
                             )
                         });
-                        return Ok(Some(HookResult::blocked(message)));
-                    } else if rule.action == "allow" && matches {
-                        // Allow and stop checking further rules for this command
-                        return Ok(None);
+                        pending_ask = Some(ask_tool_use(message));
                     }
-                }
-                // Skip file-path validation for Bash command rules
-                continue;
-            }
-
-            // Extract file path if available
-            if let Some(file_path) = extract_file_path(&payload.tool_input) {
-                let matches = Pattern::new(&rule.pattern)?.matches(&file_path);
-
-                if (rule.action == "block" && matches) || (rule.action == "allow" && !matches) {
+                } else if rule.action == "allow" && !matches {
                     let message = rule.message.clone().unwrap_or_else(|| {
                         format!(
-                            "Tool usage blocked by preToolUse.toolUsageValidation rule: {}",
-                            rule.pattern
+                            "Bash command {}does not match preToolUse.toolUsageValidation allow rule pattern: {}",
+                            if warn { "" } else { "blocked: " },
+                            pattern
                         )
                     });
-                    return Ok(Some(HookResult::blocked(message)));
+                    let result = HookResult::blocked(message)
+                        .with_mode(rule.mode.as_deref())
+                        .with_severity(rule.severity.as_deref());
+                    if let Some(result) = soft_blocks.defer(config, result) {
+                        return Ok(Some(result));
+                    }
+                } else if rule.action == "allow" && matches {
+                    // Allow and stop checking further rules for this command
+                    return Ok(soft_blocks.finish(pending_ask));
+                }
+            }
+            // Skip file-path validation for Bash command rules
+            continue;
+        }
+
+        // Extract file path if available
+        if let Some(file_path) = extract_file_path(&payload.tool_input) {
+            let matches = Pattern::new(&rule.pattern)?.matches(&file_path);
+            trace::record(
+                rule_name,
+                format!(
+                    "{} pattern '{}' against {file_path} ({})",
+                    rule.tool, rule.pattern, rule.action
+                ),
+                matches,
+            );
+
+            if (rule.action == "block" && matches) || (rule.action == "allow" && !matches) {
+                let message = rule.message.clone().unwrap_or_else(|| {
+                    format!(
+                        "Tool usage {} preToolUse.toolUsageValidation rule: {}",
+                        if warn { "flagged by" } else { "blocked by" },
+                        rule.pattern
+                    )
+                });
+                let result = HookResult::blocked(message)
+                    .with_mode(rule.mode.as_deref())
+                    .with_severity(rule.severity.as_deref());
+                if let Some(result) = soft_blocks.defer(config, result) {
+                    return Ok(Some(result));
+                }
+            } else if rule.action == "ask" && matches && pending_ask.is_none() {
+                let message = rule.message.clone().unwrap_or_else(|| {
+                    format!(
+                        "Tool usage requires confirmation by preToolUse.toolUsageValidation rule: {}",
+                        rule.pattern
+                    )
+                });
+                pending_ask = Some(ask_tool_use(message));
+            }
+        }
+    }
+
+    Ok(soft_blocks.finish(pending_ask))
+}
+
+/// Result of matching a `commandPattern` against a Bash command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BashPatternMatch {
+    /// The raw command or at least one of its simple commands matches
+    pub any: bool,
+    /// Every simple command in the command matches
+    pub all: bool,
+}
+
+/// Match a `commandPattern` glob against a Bash command and each simple command in it
+///
+/// The command is split with [`crate::shell::parse_simple_commands`], so chained,
+/// piped, substituted and wrapped commands (`env`, `sudo`, `xargs`, `bash -c`) are all
+/// checked. In `prefix` mode progressively longer word prefixes of each command are
+/// tested; in `full` mode the whole command must match.
+///
+/// # Errors
+///
+/// Returns an error if the glob pattern is invalid.
+pub fn match_bash_command_pattern(
+    pattern: &str,
+    match_mode: &str,
+    command: &str,
+) -> Result<BashPatternMatch> {
+    let glob = Pattern::new(pattern)?;
+    let matches = |text: &str| {
+        if match_mode == "prefix" {
+            let words: Vec<&str> = text.split_whitespace().collect();
+            (1..=words.len()).any(|i| glob.matches(&words[..i].join(" ")))
+        } else {
+            glob.matches(text)
+        }
+    };
+
+    let simple_commands = simple_command_texts(command);
+    if simple_commands.is_empty() {
+        let matched = matches(command);
+        return Ok(BashPatternMatch {
+            any: matched,
+            all: matched,
+        });
+    }
+
+    Ok(BashPatternMatch {
+        any: matches(command) || simple_commands.iter().any(|c| matches(c)),
+        all: simple_commands.iter().all(|c| matches(c)),
+    })
+}
+
+/// Build a `PreToolUse` result that asks the user to confirm the tool call
+fn ask_tool_use(message: String) -> HookResult {
+    HookResult::pre_tool_use_decision(PermissionDecision::Ask, Some(message))
+}
+
+/// Tool input produced by `preToolUse.rewrite` rules
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolInputRewrite {
+    /// Full tool input with the rewritten command or file path
+    pub updated_input: HashMap<String, Value>,
+    /// `ask` unless every applied rule has `action: allow`
+    pub decision: PermissionDecision,
+    /// One message per applied rule
+    pub messages: Vec<String>,
+}
+
+/// Apply `preToolUse.rewrite` rules to a tool input
+///
+/// Rules run in order and each sees the output of the previous one. Bash rules match
+/// the command with `commandPattern`; other rules match the file path with `pattern`
+/// (absolute or relative to `project_root`). Returns `None` when nothing changed.
+///
+/// # Errors
+///
+/// Returns an error if a glob pattern or `find` regex is invalid.
+pub fn apply_rewrite_rules<S: std::hash::BuildHasher>(
+    rules: &[RewriteRule],
+    tool_name: &str,
+    tool_input: &HashMap<String, Value, S>,
+    project_root: &Path,
+) -> Result<Option<ToolInputRewrite>> {
+    let mut updated_input: HashMap<String, Value> = tool_input
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let mut decision = PermissionDecision::Allow;
+    let mut messages = Vec::new();
+
+    for (idx, rule) in rules.iter().enumerate() {
+        if rule.tool != tool_name && rule.tool != "*" {
+            continue;
+        }
+
+        // `allow` is only safe when every simple command in a Bash chain matches
+        let mut whole_command_matched = true;
+        let applied = 'rule: {
+            let (key, value) = if let Some(pattern) = rule
+                .command_pattern
+                .as_ref()
+                .filter(|_| tool_name == "Bash")
+            {
+                let Some(command) = extract_bash_command(&updated_input) else {
+                    break 'rule None;
+                };
+                let matched = match_bash_command_pattern(pattern, "full", &command)?;
+                if !matched.any {
+                    break 'rule None;
+                }
+                whole_command_matched = matched.all;
+                ("command", command)
+            } else if let Some(pattern) = &rule.pattern {
+                let Some(file_path) = extract_file_path(&updated_input) else {
+                    break 'rule None;
+                };
+                let glob = Pattern::new(pattern)?;
+                let relative = Path::new(&file_path)
+                    .strip_prefix(project_root)
+                    .ok()
+                    .map(|p| p.to_string_lossy().to_string());
+                if !glob.matches(&file_path) && !relative.is_some_and(|r| glob.matches(&r)) {
+                    break 'rule None;
                 }
+                let key = if updated_input.contains_key("file_path") {
+                    "file_path"
+                } else {
+                    "notebook_path"
+                };
+                (key, file_path)
+            } else {
+                break 'rule None;
+            };
+
+            let find = regex::Regex::new(&rule.find)
+                .with_context(|| format!("Invalid regex in preToolUse.rewrite: {}", rule.find))?;
+            let replacement = rule.replace.replace(
+                "{project_root}",
+                &project_root.to_string_lossy().replace('$', "$$"),
+            );
+            let rewritten = find.replace_all(&value, replacement.as_str()).to_string();
+            if rewritten == value {
+                break 'rule None;
             }
+
+            Some((key, value, rewritten))
+        };
+        trace::record(
+            format!("preToolUse.rewrite[{idx}]"),
+            format!("find '{}' for {}", rule.find, rule.tool),
+            applied.is_some(),
+        );
+        let Some((key, value, rewritten)) = applied else {
+            continue;
+        };
+
+        if rule.action.as_deref() != Some("allow") || !whole_command_matched {
+            decision = PermissionDecision::Ask;
         }
+        messages.push(rule.message.clone().unwrap_or_else(|| {
+            format!("Rewritten by preToolUse.rewrite rule: {value} -> {rewritten}")
+        }));
+        updated_input.insert(key.to_string(), Value::String(rewritten));
     }
 
-    Ok(None)
+    if messages.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(ToolInputRewrite {
+        updated_input,
+        decision,
+        messages,
+    }))
 }
 
+/// Built-in markers that identify generated files, matched case-insensitively
+const DEFAULT_GENERATED_MARKERS: [&str; 9] = [
+    "DO NOT EDIT",
+    "do not edit",
+    "Code generated by",
+    "Auto-generated",
+    "Autogenerated",
+    "Generated code",
+    "@generated",
+    "This file is generated",
+    "This file was generated",
+];
+
+/// Number of lines scanned for generated-file markers unless `scanLines` is set
+const DEFAULT_GENERATED_SCAN_LINES: usize = 100;
+
 /// Check if file contains auto-generated markers
 ///
-/// Returns the marker found if file contains generation markers, None otherwise
+/// Uses the built-in markers and scans the first 100 lines. Returns the marker found
+/// if file contains generation markers, None otherwise
 #[must_use]
+#[allow(dead_code)]
 pub fn check_generated_file_markers(content: &str) -> Option<String> {
-    // Check first 100 lines for markers
-    let lines_to_check: Vec<&str> = content.lines().take(100).collect();
-    let content_to_check = lines_to_check.join("\n");
-
-    // List of markers to check (case-insensitive)
-    let markers = [
-        "DO NOT EDIT",
-        "do not edit",
-        "Code generated by",
-        "Auto-generated",
-        "Autogenerated",
-        "Generated code",
-        "@generated",
-        "This file is generated",
-        "This file was generated",
-    ];
-
-    for marker in &markers {
-        if content_to_check
-            .to_lowercase()
-            .contains(&marker.to_lowercase())
-        {
-            // Find the actual marker text in the file for accurate reporting
-            for line in &lines_to_check {
-                if line.to_lowercase().contains(&marker.to_lowercase()) {
-                    // Extract the actual marker phrase from the line
-                    let lower_line = line.to_lowercase();
-                    let lower_marker = marker.to_lowercase();
-                    if let Some(pos) = lower_line.find(&lower_marker) {
-                        // Get the actual text from the original line
-                        let actual_marker = &line[pos..pos + marker.len()];
-                        return Some(actual_marker.to_string());
+    find_generated_file_marker(content, &GeneratedFilesConfig::default()).unwrap_or_default()
+}
+
+/// Find the first generated-file marker in the top of `content`
+///
+/// Checks the built-in markers (unless `defaultMarkers` is false) and then the
+/// configured ones, each against the first `scanLines` lines. Text markers match
+/// case-insensitively and return the text as written in the file; regex markers
+/// return the matched text.
+///
+/// # Errors
+///
+/// Returns an error if a regex marker is invalid.
+pub fn find_generated_file_marker(
+    content: &str,
+    settings: &GeneratedFilesConfig,
+) -> Result<Option<String>> {
+    let scan_lines = settings.scan_lines.unwrap_or(DEFAULT_GENERATED_SCAN_LINES);
+    let lines: Vec<&str> = content.lines().take(scan_lines).collect();
+
+    let defaults: Vec<GeneratedFileMarker> = if settings.default_markers {
+        DEFAULT_GENERATED_MARKERS
+            .iter()
+            .map(|marker| GeneratedFileMarker::Text((*marker).to_string()))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    for marker in defaults.iter().chain(&settings.markers) {
+        match marker {
+            GeneratedFileMarker::Text(text) => {
+                let lower_marker = text.to_lowercase();
+                for line in &lines {
+                    if let Some(pos) = line.to_lowercase().find(&lower_marker) {
+                        // Report the marker as it is written in the file
+                        let actual = line.get(pos..pos + text.len()).unwrap_or(text);
+                        return Ok(Some(actual.to_string()));
                     }
                 }
             }
-            // Fallback to the marker as-is if we can't extract the exact text
-            return Some((*marker).to_string());
+            GeneratedFileMarker::Regex { regex } => {
+                let regex = regex::Regex::new(regex).with_context(|| {
+                    format!("Invalid regex in preToolUse.generatedFiles.markers: {regex}")
+                })?;
+                if let Some(found) = lines.iter().find_map(|line| regex.find(line)) {
+                    return Ok(Some(found.as_str().to_string()));
+                }
+            }
         }
     }
 
-    None
+    Ok(None)
+}
+
+/// Check whether `preToolUse.generatedFiles` `include`/`exclude` select a file for the
+/// generated-file check
+///
+/// # Errors
+///
+/// Returns an error if a glob pattern is invalid.
+pub fn generated_file_check_applies(
+    settings: &GeneratedFilesConfig,
+    file_path: &str,
+    cwd: &Path,
+) -> Result<bool> {
+    for pattern in &settings.exclude {
+        if glob_matches_file(pattern, file_path, cwd)? {
+            return Ok(false);
+        }
+    }
+    if settings.include.is_empty() {
+        return Ok(true);
+    }
+    for pattern in &settings.include {
+        if glob_matches_file(pattern, file_path, cwd)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Find the `preToolUse.generatedFiles.sourceHint` entry for a generated file
+///
+/// Globs are tried in sorted order so the result does not depend on map ordering.
+///
+/// # Errors
+///
+/// Returns an error if a glob pattern is invalid.
+pub fn generated_file_source_hint<'a>(
+    settings: &'a GeneratedFilesConfig,
+    file_path: &str,
+    cwd: &Path,
+) -> Result<Option<&'a str>> {
+    let mut patterns: Vec<&String> = settings.source_hint.keys().collect();
+    patterns.sort();
+    for pattern in patterns {
+        if glob_matches_file(pattern, file_path, cwd)? {
+            return Ok(Some(settings.source_hint[pattern].as_str()));
+        }
+    }
+    Ok(None)
+}
+
+/// Match a glob against a file path as given, relative to `cwd`, and resolved onto `cwd`
+fn glob_matches_file(pattern: &str, file_path: &str, cwd: &Path) -> Result<bool> {
+    let resolved_path = cwd.join(file_path);
+    let relative_path = resolved_path
+        .strip_prefix(cwd)
+        .unwrap_or(resolved_path.as_path())
+        .to_string_lossy()
+        .to_string();
+    matches_uneditable_pattern(
+        file_path,
+        &relative_path,
+        &resolved_path.to_string_lossy(),
+        pattern,
+    )
 }
 
 /// Check if file is auto-generated and should not be edited
@@ -1617,18 +3893,36 @@ pub async fn check_auto_generated_file(payload: &PreToolUsePayload) -> Result<Op
         return Ok(None);
     }
 
+    let settings = &config.pre_tool_use.generated_files;
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    if !generated_file_check_applies(settings, &file_path, &cwd)? {
+        return Ok(None);
+    }
+
     // Read file content
     let Ok(content) = fs::read_to_string(&file_path) else {
         return Ok(None); // File doesn't exist or can't be read, allow operation
     };
 
     // Check for auto-generated markers
-    if let Some(marker) = check_generated_file_markers(&content) {
+    let marker = find_generated_file_marker(&content, settings)?;
+    trace::record(
+        "preToolUse.preventGeneratedFileEdits",
+        format!("generated-file markers in {file_path}"),
+        marker.is_some(),
+    );
+    if let Some(marker) = marker {
+        let source_hint = generated_file_source_hint(settings, &file_path, &cwd)?;
         // Use custom message or default
         let message = if let Some(custom_msg) = &config.pre_tool_use.generated_file_message {
             custom_msg
                 .replace("{file_path}", &file_path)
                 .replace("{marker}", &marker)
+                .replace("{source_hint}", source_hint.unwrap_or_default())
+        } else if let Some(source_hint) = source_hint {
+            format!(
+                "BLOCKED: File '{file_path}' is auto-generated (contains '{marker}'). This file should NEVER be edited directly. Edit {source_hint} instead and regenerate this file."
+            )
         } else {
             format!(
                 "BLOCKED: File '{file_path}' is auto-generated (contains '{marker}'). This file should NEVER be edited directly. Modifications should be made to the source/template that generates this file."
@@ -1646,6 +3940,509 @@ pub async fn check_auto_generated_file(payload: &PreToolUsePayload) -> Result<Op
     Ok(None)
 }
 
+/// Collect the text a file-modifying tool call writes
+///
+/// Returns Write `content`, Edit `new_string`, every `MultiEdit` `edits[].new_string`
+/// (one entry per edit), and `NotebookEdit` `new_source` for replaced or inserted cells.
+/// Other tools and notebook cell deletes write nothing.
+#[must_use]
+pub fn collect_written_content<S: std::hash::BuildHasher>(
+    tool_name: &str,
+    tool_input: &HashMap<String, Value, S>,
+) -> Vec<String> {
+    match tool_name {
+        "Write" => tool_input
+            .get("content")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .into_iter()
+            .collect(),
+        "Edit" => EditOperation::from_tool_input(tool_input)
+            .map(|edit| edit.new_string)
+            .into_iter()
+            .collect(),
+        "MultiEdit" => MultiEditInput::from_tool_input(tool_input)
+            .map(|input| {
+                input
+                    .edits
+                    .into_iter()
+                    .map(|edit| edit.new_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        "NotebookEdit" => NotebookEditInput::from_tool_input(tool_input)
+            .filter(|input| input.edit_mode != NotebookEditMode::Delete)
+            .map(|input| input.new_source)
+            .into_iter()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Contents a file will have after a Write, Edit, or `MultiEdit`
+///
+/// `current` is the file's existing content, if any. Returns `None` for other tools,
+/// or when an edit's `old_string` cannot be applied to the existing content.
+#[must_use]
+pub fn projected_file_content<S: std::hash::BuildHasher>(
+    tool_name: &str,
+    tool_input: &HashMap<String, Value, S>,
+    current: Option<&str>,
+) -> Option<String> {
+    let current = current.map(str::to_string);
+    match tool_name {
+        "Write" => tool_input
+            .get("content")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        "Edit" => EditOperation::from_tool_input(tool_input)?.apply(current),
+        "MultiEdit" => MultiEditInput::from_tool_input(tool_input)?
+            .edits
+            .iter()
+            .try_fold(current, |content, edit| edit.apply(content).map(Some))?,
+        _ => None,
+    }
+}
+
+/// Describe why written text looks binary, if it does
+///
+/// Text containing NUL bytes or U+FFFD replacement characters (left behind by invalid
+/// UTF-8), or where more than 10% of characters are control characters other than
+/// whitespace, is treated as binary.
+#[must_use]
+pub fn binary_content_reason(text: &str) -> Option<&'static str> {
+    if text.contains('\0') {
+        return Some("contains NUL bytes");
+    }
+    if text.contains('\u{FFFD}') {
+        return Some("contains invalid UTF-8");
+    }
+    let total = text.chars().count();
+    let control = text
+        .chars()
+        .filter(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x0c'))
+        .count();
+    (control * 10 > total).then_some("is mostly control characters")
+}
+
+/// Check a file write against `preToolUse.limits`
+///
+/// Size limits apply to the file a Write creates and to Edit/`MultiEdit` calls that grow
+/// a file. Returns the block reason for the first limit exceeded.
+#[must_use]
+pub fn check_write_limits<S: std::hash::BuildHasher>(
+    limits: &WriteLimitsConfig,
+    tool_name: &str,
+    file_path: &str,
+    tool_input: &HashMap<String, Value, S>,
+    current: Option<&str>,
+) -> Option<String> {
+    if limits.prevent_binary {
+        if let Some(reason) = collect_written_content(tool_name, tool_input)
+            .iter()
+            .find_map(|text| binary_content_reason(text))
+        {
+            return Some(format!(
+                "Blocked {tool_name} operation: written content {reason}. preToolUse.limits.preventBinary only allows text files. File: {file_path}"
+            ));
+        }
+    }
+
+    let projected = projected_file_content(tool_name, tool_input, current)?;
+
+    if limits.prevent_truncation
+        && current.is_some_and(|c| !c.trim().is_empty())
+        && projected.trim().is_empty()
+    {
+        return Some(format!(
+            "Blocked {tool_name} operation: preToolUse.limits.preventTruncation prevents emptying an existing file. Delete the file explicitly if that is intended. File: {file_path}"
+        ));
+    }
+
+    let grows = tool_name == "Write" || projected.len() > current.map_or(0, str::len);
+    if !grows {
+        return None;
+    }
+    if let Some(max) = limits.max_bytes.filter(|max| projected.len() > *max) {
+        return Some(format!(
+            "Blocked {tool_name} operation: file would be {} bytes, over the preToolUse.limits.maxBytes limit of {max}. File: {file_path}",
+            projected.len()
+        ));
+    }
+    let lines = projected.lines().count();
+    if let Some(max) = limits.max_lines.filter(|max| lines > *max) {
+        return Some(format!(
+            "Blocked {tool_name} operation: file would be {lines} lines, over the preToolUse.limits.maxLines limit of {max}. File: {file_path}"
+        ));
+    }
+
+    None
+}
+
+/// Check `preToolUse.limits` for a file-modifying tool call
+///
+/// # Errors
+///
+/// Returns an error if configuration loading fails.
+async fn check_write_limits_for_payload(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
+    let (config, _config_path) = get_config().await?;
+    let limits = &config.pre_tool_use.limits;
+    if limits.max_bytes.is_none()
+        && limits.max_lines.is_none()
+        && !limits.prevent_binary
+        && !limits.prevent_truncation
+    {
+        return Ok(None);
+    }
+    let Some(file_path) = extract_file_path(&payload.tool_input) else {
+        return Ok(None);
+    };
+
+    let current = fs::read_to_string(&file_path).ok();
+    let violation = check_write_limits(
+        limits,
+        &payload.tool_name,
+        &file_path,
+        &payload.tool_input,
+        current.as_deref(),
+    );
+    trace::record(
+        "preToolUse.limits",
+        format!("size, binary, and truncation limits for {file_path}"),
+        violation.is_some(),
+    );
+    let Some(reason) = violation else {
+        return Ok(None);
+    };
+
+    eprintln!(
+        "PreToolUse blocked by preToolUse.limits: tool_name={}, file_path={}",
+        payload.tool_name, file_path
+    );
+    Ok(Some(HookResult::blocked(reason)))
+}
+
+/// Find the `preToolUse.notebookCells` rule that blocks a `NotebookEdit` call
+///
+/// `notebook` is the parsed notebook, used to look up the target cell's tags and
+/// metadata. The target cell is found by its `id`, falling back to a `cell-N` or `N`
+/// index. Rules with `tag` or `metadata` never match when the cell cannot be found.
+///
+/// # Errors
+///
+/// Returns an error if a rule contains an invalid glob pattern.
+pub fn find_notebook_cell_rule<'a>(
+    rules: &'a [NotebookCellRule],
+    edit: &NotebookEditInput,
+    notebook: Option<&Value>,
+    cwd: &Path,
+) -> Result<Option<&'a NotebookCellRule>> {
+    let resolved_path = cwd.join(&edit.notebook_path);
+    let relative_path = resolved_path
+        .strip_prefix(cwd)
+        .unwrap_or(resolved_path.as_path())
+        .to_string_lossy()
+        .to_string();
+
+    let cell = edit.cell_id.as_deref().and_then(|cell_id| {
+        let cells = notebook?.get("cells")?.as_array()?;
+        cells
+            .iter()
+            .find(|cell| cell.get("id").and_then(Value::as_str) == Some(cell_id))
+            .or_else(|| {
+                let index = cell_id.strip_prefix("cell-").unwrap_or(cell_id);
+                cells.get(index.parse::<usize>().ok()?)
+            })
+    });
+    let metadata = cell.and_then(|cell| cell.get("metadata"));
+
+    for (idx, rule) in rules.iter().enumerate() {
+        let matched = notebook_cell_rule_matches(
+            rule,
+            edit,
+            &relative_path,
+            &resolved_path.to_string_lossy(),
+            metadata,
+        )?;
+        trace::record(
+            format!("preToolUse.notebookCells[{idx}]"),
+            format!(
+                "{} of cell {} in {}",
+                edit.edit_mode.as_str(),
+                edit.cell_id.as_deref().unwrap_or("(new)"),
+                edit.notebook_path
+            ),
+            matched,
+        );
+        if matched {
+            return Ok(Some(rule));
+        }
+    }
+
+    Ok(None)
+}
+
+fn notebook_cell_rule_matches(
+    rule: &NotebookCellRule,
+    edit: &NotebookEditInput,
+    relative_path: &str,
+    resolved_path: &str,
+    metadata: Option<&Value>,
+) -> Result<bool> {
+    if !rule.operations.is_empty()
+        && !rule
+            .operations
+            .iter()
+            .any(|op| op == edit.edit_mode.as_str())
+    {
+        return Ok(false);
+    }
+    if let Some(pattern) = &rule.pattern {
+        if !matches_uneditable_pattern(&edit.notebook_path, relative_path, resolved_path, pattern)?
+        {
+            return Ok(false);
+        }
+    }
+    if let Some(tag) = &rule.tag {
+        let tagged = metadata
+            .and_then(|m| m.get("tags"))
+            .and_then(Value::as_array)
+            .is_some_and(|tags| tags.iter().any(|t| t.as_str() == Some(tag)));
+        if !tagged {
+            return Ok(false);
+        }
+    }
+    for (path, glob_pattern) in &rule.metadata {
+        let pattern = Pattern::new(glob_pattern)
+            .with_context(|| format!("Invalid glob pattern: {glob_pattern}"))?;
+        let value = metadata.and_then(|m| m.pointer(&format!("/{}", path.replace('.', "/"))));
+        if !value.is_some_and(|v| pattern.matches(&value_text(v))) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Check `preToolUse.notebookCells` for a `NotebookEdit` call
+///
+/// # Errors
+///
+/// Returns an error if configuration loading fails or a rule pattern is invalid.
+async fn check_notebook_cell_rules(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
+    let (config, _config_path) = get_config().await?;
+    if config.pre_tool_use.notebook_cells.is_empty() {
+        return Ok(None);
+    }
+    let Some(edit) = NotebookEditInput::from_tool_input(&payload.tool_input) else {
+        return Ok(None);
+    };
+
+    let notebook = fs::read_to_string(&edit.notebook_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok());
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    let Some(rule) = find_notebook_cell_rule(
+        &config.pre_tool_use.notebook_cells,
+        &edit,
+        notebook.as_ref(),
+        &cwd,
+    )?
+    else {
+        return Ok(None);
+    };
+
+    let operation = edit.edit_mode.as_str();
+    let cell = edit.cell_id.as_deref().unwrap_or("(start of notebook)");
+    eprintln!(
+        "PreToolUse blocked by preToolUse.notebookCells: notebook_path={}, cell_id={}, operation={}",
+        edit.notebook_path, cell, operation
+    );
+    let message = rule.message.clone().unwrap_or_else(|| {
+        let verb = if rule.severity.as_deref() == Some("warn") {
+            "NotebookEdit"
+        } else {
+            "Blocked NotebookEdit"
+        };
+        format!(
+            "{verb} {operation} at cell '{cell}': protected by a preToolUse.notebookCells rule. File: {}",
+            edit.notebook_path
+        )
+    });
+    Ok(Some(
+        HookResult::blocked(message).with_severity(rule.severity.as_deref()),
+    ))
+}
+
+/// Outcome of evaluating `preToolUse.contentRules` against written text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentScreening {
+    /// Reason from the first matching `block` rule
+    pub block_reason: Option<String>,
+    /// Messages from matching `warn` rules
+    pub warnings: Vec<String>,
+}
+
+/// Evaluate `preToolUse.contentRules` for text written to `file_path`
+///
+/// Rules whose glob matches the file are checked in order against each piece of
+/// written text, so every `MultiEdit` edit is evaluated on its own. The first matching
+/// `block` rule stops evaluation; `warn` rules collect messages. Default messages name
+/// the regex and the edit and line of the first match.
+///
+/// # Errors
+///
+/// Returns an error if a glob or regex in a rule is invalid.
+pub fn evaluate_content_rules(
+    rules: &[ContentRule],
+    file_path: &str,
+    contents: &[String],
+    cwd: &Path,
+) -> Result<ContentScreening> {
+    let resolved_path = cwd.join(file_path);
+    let relative_path = resolved_path
+        .strip_prefix(cwd)
+        .unwrap_or(resolved_path.as_path())
+        .to_string_lossy()
+        .to_string();
+
+    let mut screening = ContentScreening::default();
+    for (idx, rule) in rules.iter().enumerate() {
+        let rule_name = format!("preToolUse.contentRules[{idx}]");
+        let description = format!(
+            "regex '{}' in files matching '{}' ({})",
+            rule.regex, rule.pattern, rule.action
+        );
+        if !matches_uneditable_pattern(
+            file_path,
+            &relative_path,
+            &resolved_path.to_string_lossy(),
+            &rule.pattern,
+        )? {
+            trace::record(rule_name, description, false);
+            continue;
+        }
+
+        let regex = regex::Regex::new(&rule.regex)
+            .with_context(|| format!("Invalid regex in preToolUse.contentRules: {}", rule.regex))?;
+        let found = contents
+            .iter()
+            .enumerate()
+            .find_map(|(idx, content)| regex.find(content).map(|found| (idx, content, found)));
+        trace::record(rule_name, description, found.is_some());
+        let Some((idx, content, found)) = found else {
+            continue;
+        };
+
+        let line = content[..found.start()].matches('\n').count() + 1;
+        let location = if contents.len() > 1 {
+            format!("edit {}, line {line}", idx + 1)
+        } else {
+            format!("line {line}")
+        };
+        let message = rule.message.clone().unwrap_or_else(|| {
+            format!(
+                "Content matches preToolUse.contentRules regex '{}' on {location}. File: {file_path}",
+                rule.regex
+            )
+        });
+        if rule.action == "block" {
+            screening.block_reason = Some(message);
+            return Ok(screening);
+        }
+        screening.warnings.push(message);
+    }
+
+    Ok(screening)
+}
+
+/// Check `preToolUse.contentRules` for the text a file-modifying tool writes
+///
+/// # Errors
+///
+/// Returns an error if configuration loading fails or a rule pattern is invalid.
+async fn check_content_rules(payload: &PreToolUsePayload) -> Result<ContentScreening> {
+    let (config, _config_path) = get_config().await?;
+    let Some(file_path) = extract_file_path(&payload.tool_input) else {
+        return Ok(ContentScreening::default());
+    };
+    if config.pre_tool_use.content_rules.is_empty() {
+        return Ok(ContentScreening::default());
+    }
+
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    let contents = collect_written_content(&payload.tool_name, &payload.tool_input);
+    let screening = evaluate_content_rules(
+        &config.pre_tool_use.content_rules,
+        &file_path,
+        &contents,
+        &cwd,
+    )?;
+    if screening.block_reason.is_some() {
+        eprintln!(
+            "PreToolUse blocked by preToolUse.contentRules: tool_name={}, file_path={}",
+            payload.tool_name, file_path
+        );
+    }
+    Ok(screening)
+}
+
+/// Block writes whose content contains credentials or other secrets
+///
+/// # Errors
+///
+/// Returns an error if configuration loading fails or the allowlist file cannot be read.
+async fn check_secret_content(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
+    let (config, config_path) = get_config().await?;
+    let settings = &config.pre_tool_use.secret_scanning;
+    if !settings.enabled {
+        return Ok(None);
+    }
+
+    let scanner = SecretScanner::new(settings, get_config_dir(config_path))?;
+    let contents = collect_written_content(&payload.tool_name, &payload.tool_input);
+    let mut lines = Vec::new();
+    for (idx, content) in contents.iter().enumerate() {
+        for finding in scanner.scan(content) {
+            let location = if contents.len() > 1 {
+                format!("edit {}, line {}", idx + 1, finding.line)
+            } else {
+                format!("line {}", finding.line)
+            };
+            lines.push(format!(
+                "  - {} on {location} ({})",
+                finding.kind, finding.preview
+            ));
+        }
+    }
+    let file_path = extract_file_path(&payload.tool_input).unwrap_or_default();
+    trace::record(
+        "preToolUse.secretScanning",
+        format!("{} written text(s) for {file_path}", contents.len()),
+        !lines.is_empty(),
+    );
+    if lines.is_empty() {
+        return Ok(None);
+    }
+
+    eprintln!(
+        "PreToolUse blocked by preToolUse.secretScanning: tool_name={}, file_path={}, findings={}",
+        payload.tool_name,
+        file_path,
+        lines.len()
+    );
+
+    let mut message = format!(
+        "Blocked {} operation: possible secrets in content for {file_path}:\n{}\nRead secrets from environment variables or a secrets manager instead of writing them to disk. Add known-safe values to preToolUse.secretScanning.allowlist.",
+        payload.tool_name,
+        lines.join("\n")
+    );
+    if let Some(custom) = &settings.message {
+        message.push('\n');
+        message.push_str(custom);
+    }
+    Ok(Some(HookResult::blocked(message)))
+}
+
 /// Check if a file is git-ignored and should be protected.
 ///
 /// This check blocks both creation of new files and modification of existing files
@@ -1659,6 +4456,21 @@ pub async fn check_auto_generated_file(payload: &PreToolUsePayload) -> Result<Op
 ///
 /// Returns an error if configuration loading fails or gitignore check fails.
 async fn check_git_ignored_file(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
+    // Extract file path from tool input
+    let file_path = extract_file_path(&payload.tool_input);
+    let Some(file_path) = file_path else {
+        return Ok(None);
+    };
+
+    check_git_ignored_path(&payload.tool_name, &file_path).await
+}
+
+/// Check a single written file against `preventUpdateGitIgnored`
+///
+/// # Errors
+///
+/// Returns an error if configuration loading fails or git-ignore evaluation fails.
+async fn check_git_ignored_path(tool_name: &str, file_path: &str) -> Result<Option<HookResult>> {
     let (config, config_path) = get_config().await?;
 
     // Only check if the feature is enabled
@@ -1666,12 +4478,6 @@ async fn check_git_ignored_file(payload: &PreToolUsePayload) -> Result<Option<Ho
         return Ok(None);
     }
 
-    // Extract file path from tool input
-    let file_path = extract_file_path(&payload.tool_input);
-    let Some(file_path) = file_path else {
-        return Ok(None);
-    };
-
     // Find the actual git repository root by walking up from config path
     // This is more reliable than just using config path's parent
     let config_dir = get_config_dir(config_path);
@@ -1685,10 +4491,15 @@ async fn check_git_ignored_file(payload: &PreToolUsePayload) -> Result<Option<Ho
 
     // Resolve the file path to check
     let cwd = std::env::current_dir().context("Failed to get current working directory")?;
-    let resolved_path = cwd.join(&file_path);
+    let resolved_path = cwd.join(file_path);
 
     // Check if the file is git-ignored
     let (is_ignored, pattern) = is_path_git_ignored(&resolved_path, &repo_root)?;
+    trace::record(
+        "preToolUse.preventUpdateGitIgnored",
+        format!("{file_path} against .gitignore"),
+        is_ignored,
+    );
 
     if is_ignored {
         let pattern_display =
@@ -1708,15 +4519,15 @@ async fn check_git_ignored_file(payload: &PreToolUsePayload) -> Result<Option<Ho
             3. Set preventUpdateGitIgnored: false in your config",
             file_path,
             pattern_display,
-            Path::new(&file_path)
+            Path::new(file_path)
                 .file_name()
                 .and_then(|n| n.to_str())
-                .unwrap_or(&file_path)
+                .unwrap_or(file_path)
         );
 
         eprintln!(
             "PreToolUse blocked git-ignored file: tool_name={}, file_path={}, pattern={}",
-            payload.tool_name, file_path, pattern_display
+            tool_name, file_path, pattern_display
         );
 
         return Ok(Some(HookResult::blocked(message)));
@@ -1800,6 +4611,7 @@ mod tests {
             show_errors: true,
             show_success: true,
             show_system_events: true,
+            routes: Vec::new(),
         };
 
         // All notification types should be allowed
@@ -1813,6 +4625,7 @@ mod tests {
             show_errors: true,
             show_success: false,
             show_system_events: false,
+            routes: Vec::new(),
         };
 
         // This tests the is_enabled_for method - flags are checked in send_notification
@@ -1826,6 +4639,7 @@ mod tests {
             show_errors: false,
             show_success: true,
             show_system_events: false,
+            routes: Vec::new(),
         };
 
         assert!(config_success_only.is_enabled_for("PreToolUse"));
@@ -1838,6 +4652,7 @@ mod tests {
             show_errors: false,
             show_success: false,
             show_system_events: true,
+            routes: Vec::new(),
         };
 
         assert!(config_system_only.is_enabled_for("PreToolUse"));
@@ -2,6 +2,8 @@
 pub mod config;
 pub mod gitignore;
 pub mod hooks;
+pub mod permissions;
+pub mod quotas;
 
 /// Schema generation and validation utilities.
 ///
@@ -28,4 +30,7 @@ pub mod hooks;
 /// write_schema_to_file(&schema, &output_path).unwrap();
 /// ```
 pub mod schema;
+pub mod secrets;
+pub mod shell;
+pub mod trace;
 pub mod types;
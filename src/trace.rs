@@ -0,0 +1,86 @@
+//! Rule evaluation tracing for `conclaude explain`.
+//!
+//! Hook checks call [`record`] for every rule they evaluate. Recording is off unless
+//! [`start`] has been called, so live hooks pay nothing but a lock check.
+
+use std::sync::Mutex;
+
+static TRACE: Mutex<Option<Vec<TraceEntry>>> = Mutex::new(None);
+
+/// One evaluated rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Config location of the rule, e.g. `preToolUse.uneditableFiles[0]`
+    pub rule: String,
+    /// What the rule checks and against which input
+    pub description: String,
+    /// Whether the rule matched
+    pub matched: bool,
+}
+
+/// Start recording evaluated rules, discarding any previous trace
+pub fn start() {
+    if let Ok(mut trace) = TRACE.lock() {
+        *trace = Some(Vec::new());
+    }
+}
+
+/// Stop recording and return the rules evaluated since [`start`]
+#[must_use]
+pub fn finish() -> Vec<TraceEntry> {
+    TRACE
+        .lock()
+        .ok()
+        .and_then(|mut trace| trace.take())
+        .unwrap_or_default()
+}
+
+/// Whether rules are currently being recorded
+#[must_use]
+pub fn is_active() -> bool {
+    TRACE.lock().is_ok_and(|trace| trace.is_some())
+}
+
+/// Record an evaluated rule when tracing is active
+pub fn record(rule: impl Into<String>, description: impl Into<String>, matched: bool) {
+    if let Ok(mut trace) = TRACE.lock() {
+        if let Some(entries) = trace.as_mut() {
+            entries.push(TraceEntry {
+                rule: rule.into(),
+                description: description.into(),
+                matched,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_only_while_active() {
+        // Other tests may record concurrently, so only look at this test's rule
+        let own = |entries: Vec<TraceEntry>| -> Vec<TraceEntry> {
+            entries
+                .into_iter()
+                .filter(|e| e.rule == "trace.test")
+                .collect()
+        };
+        record("trace.test", "ignored", true);
+        start();
+        assert!(is_active());
+        record("trace.test", "'*.lock' against Cargo.lock", true);
+        let entries = own(finish());
+        assert!(!is_active());
+        assert_eq!(
+            entries,
+            vec![TraceEntry {
+                rule: "trace.test".to_string(),
+                description: "'*.lock' against Cargo.lock".to_string(),
+                matched: true,
+            }]
+        );
+        assert!(own(finish()).is_empty());
+    }
+}
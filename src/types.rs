@@ -2,12 +2,49 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Response structure returned by hook handlers to control execution flow.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// A result is reported to Claude Code in one of two ways. Plain results built with
+/// [`HookResult::success`] or [`HookResult::blocked`] use the exit code protocol
+/// (exit 0, or the message on stderr with exit 2). Results carrying any of the structured
+/// fields below are serialized to stdout as Claude Code's JSON hook output instead.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct HookResult {
     /// Custom message to display to the user
+    #[serde(skip)]
     pub message: Option<String>,
     /// Whether to block the current operation from proceeding
+    #[serde(skip)]
     pub blocked: Option<bool>,
+    /// Whether Claude should continue after the hook runs (`false` stops the session)
+    #[serde(rename = "continue", skip_serializing_if = "Option::is_none")]
+    pub should_continue: Option<bool>,
+    /// Message shown to the user when `continue` is false
+    #[serde(rename = "stopReason", skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    /// Hide the hook's stdout from transcript mode
+    #[serde(rename = "suppressOutput", skip_serializing_if = "Option::is_none")]
+    pub suppress_output: Option<bool>,
+    /// Warning message shown to the user, separate from feedback sent to Claude
+    #[serde(rename = "systemMessage", skip_serializing_if = "Option::is_none")]
+    pub system_message: Option<String>,
+    /// Top-level decision for events that support it (e.g., "block" for Stop or PostToolUse)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<String>,
+    /// Explanation for `decision`, fed back to Claude
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Event-specific output such as permission decisions or additional context
+    #[serde(rename = "hookSpecificOutput", skip_serializing_if = "Option::is_none")]
+    pub hook_specific_output: Option<HookSpecificOutput>,
+    /// `mode` override ("enforce" or "audit") of the rule that produced this result
+    #[serde(skip)]
+    pub mode: Option<String>,
+    /// `severity` ("block" or "warn") of the rule that produced this result
+    #[serde(skip)]
+    pub severity: Option<String>,
+    /// Warnings and audit-mode blocks from other rules that matched the same call
+    #[serde(skip)]
+    pub deferred: Vec<HookResult>,
 }
 
 impl HookResult {
@@ -16,6 +53,7 @@ impl HookResult {
         Self {
             message: None,
             blocked: Some(false),
+            ..Default::default()
         }
     }
 
@@ -23,8 +61,217 @@ impl HookResult {
         Self {
             message: Some(message.into()),
             blocked: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// Block with a top-level `decision: "block"` and a reason for Claude.
+    pub fn decision_block(reason: impl Into<String>) -> Self {
+        let reason = reason.into();
+        Self {
+            message: Some(reason.clone()),
+            blocked: Some(true),
+            decision: Some("block".to_string()),
+            reason: Some(reason),
+            ..Default::default()
+        }
+    }
+
+    /// Stop Claude entirely (`continue: false`), showing `stop_reason` to the user.
+    #[allow(dead_code)]
+    pub fn stop_session(stop_reason: impl Into<String>) -> Self {
+        let stop_reason = stop_reason.into();
+        Self {
+            message: Some(stop_reason.clone()),
+            blocked: Some(true),
+            should_continue: Some(false),
+            stop_reason: Some(stop_reason),
+            ..Default::default()
+        }
+    }
+
+    /// Return a `PreToolUse` permission decision with an optional reason.
+    ///
+    /// A `deny` decision is also recorded as blocked so notifications and logs treat it
+    /// like any other blocked operation.
+    #[must_use]
+    pub fn pre_tool_use_decision(decision: PermissionDecision, reason: Option<String>) -> Self {
+        Self {
+            message: reason.clone(),
+            blocked: Some(decision == PermissionDecision::Deny),
+            hook_specific_output: Some(HookSpecificOutput {
+                permission_decision: Some(decision),
+                permission_decision_reason: reason,
+                ..HookSpecificOutput::new("PreToolUse")
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Return a `PermissionRequest` decision on behalf of the user.
+    #[must_use]
+    pub fn permission_request_decision(
+        behavior: PermissionBehavior,
+        message: Option<String>,
+    ) -> Self {
+        Self {
+            message: message.clone(),
+            blocked: Some(behavior == PermissionBehavior::Deny),
+            hook_specific_output: Some(HookSpecificOutput {
+                decision: Some(PermissionRequestDecision {
+                    behavior,
+                    message,
+                    ..Default::default()
+                }),
+                ..HookSpecificOutput::new("PermissionRequest")
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Record the `mode` override of the rule that produced this result.
+    #[must_use]
+    pub fn with_mode(mut self, mode: Option<&str>) -> Self {
+        self.mode = mode.map(str::to_string);
+        self
+    }
+
+    /// Record the `severity` of the rule that produced this result.
+    #[must_use]
+    pub fn with_severity(mut self, severity: Option<&str>) -> Self {
+        self.severity = severity.map(str::to_string);
+        self
+    }
+
+    /// Whether this result came from a `severity: warn` rule.
+    #[must_use]
+    pub fn is_warning(&self) -> bool {
+        self.severity.as_deref() == Some("warn")
+    }
+
+    /// Attach a user-facing warning message.
+    #[must_use]
+    pub fn with_system_message(mut self, message: impl Into<String>) -> Self {
+        self.system_message = Some(message.into());
+        self
+    }
+
+    /// Hide this hook's stdout from the transcript.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn with_suppress_output(mut self) -> Self {
+        self.suppress_output = Some(true);
+        self
+    }
+
+    /// Attach event-specific output.
+    #[must_use]
+    pub fn with_hook_specific_output(mut self, output: HookSpecificOutput) -> Self {
+        self.hook_specific_output = Some(output);
+        self
+    }
+
+    /// Attach a replacement tool input to a `PreToolUse` decision.
+    ///
+    /// Has no effect on results without `hookSpecificOutput`.
+    #[must_use]
+    pub fn with_updated_input(mut self, updated_input: HashMap<String, serde_json::Value>) -> Self {
+        if let Some(output) = self.hook_specific_output.as_mut() {
+            output.updated_input = Some(updated_input);
         }
+        self
     }
+
+    /// Whether this result must be reported through the JSON output protocol.
+    #[must_use]
+    pub fn has_json_output(&self) -> bool {
+        self.should_continue.is_some()
+            || self.stop_reason.is_some()
+            || self.suppress_output.is_some()
+            || self.system_message.is_some()
+            || self.decision.is_some()
+            || self.reason.is_some()
+            || self.hook_specific_output.is_some()
+    }
+}
+
+/// Event-specific portion of the JSON hook output (`hookSpecificOutput`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookSpecificOutput {
+    /// Name of the hook event this output belongs to
+    pub hook_event_name: String,
+    /// `PreToolUse` decision: allow (bypass the permission prompt), deny, or ask the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_decision: Option<PermissionDecision>,
+    /// Reason for `permission_decision`, shown to Claude on deny and to the user otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_decision_reason: Option<String>,
+    /// Replacement tool input for `PreToolUse`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_input: Option<HashMap<String, serde_json::Value>>,
+    /// Extra context added for Claude (`UserPromptSubmit`, `SessionStart`, `PostToolUse`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub additional_context: Option<String>,
+    /// `PermissionRequest` decision made on behalf of the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<PermissionRequestDecision>,
+}
+
+impl HookSpecificOutput {
+    #[must_use]
+    pub fn new(hook_event_name: impl Into<String>) -> Self {
+        Self {
+            hook_event_name: hook_event_name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Output that only adds context for Claude.
+    #[must_use]
+    pub fn additional_context(
+        hook_event_name: impl Into<String>,
+        context: impl Into<String>,
+    ) -> Self {
+        Self {
+            additional_context: Some(context.into()),
+            ..Self::new(hook_event_name)
+        }
+    }
+}
+
+/// `PreToolUse` permission decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionDecision {
+    Allow,
+    Deny,
+    Ask,
+}
+
+/// Behavior for a `PermissionRequest` decision.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionBehavior {
+    #[default]
+    Allow,
+    Deny,
+}
+
+/// Decision object returned from a `PermissionRequest` hook.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionRequestDecision {
+    pub behavior: PermissionBehavior,
+    /// Replacement tool input when allowing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_input: Option<HashMap<String, serde_json::Value>>,
+    /// Message explaining a denial to Claude
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Stop Claude after a denial
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interrupt: Option<bool>,
 }
 
 /// Base fields present in all hook payloads
@@ -56,6 +303,133 @@ pub struct PreToolUsePayload {
     pub tool_use_id: Option<String>,
 }
 
+/// Deserialize a tool input map into one of the typed tool inputs below
+fn parse_tool_input<T: serde::de::DeserializeOwned, S: std::hash::BuildHasher>(
+    tool_input: &HashMap<String, serde_json::Value, S>,
+) -> Option<T> {
+    serde_json::to_value(tool_input)
+        .ok()
+        .and_then(|value| serde_json::from_value(value).ok())
+}
+
+/// A single `old_string` → `new_string` replacement made by Edit or `MultiEdit`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EditOperation {
+    /// Text to replace; empty when the edit creates a new file
+    #[serde(default)]
+    pub old_string: String,
+    /// Replacement text
+    #[serde(default)]
+    pub new_string: String,
+    /// Replace every occurrence instead of the first
+    #[serde(default)]
+    pub replace_all: bool,
+}
+
+impl EditOperation {
+    /// Parse the input of an Edit tool call
+    #[must_use]
+    pub fn from_tool_input<S: std::hash::BuildHasher>(
+        tool_input: &HashMap<String, serde_json::Value, S>,
+    ) -> Option<Self> {
+        parse_tool_input(tool_input)
+    }
+
+    /// Apply the edit to a file's content
+    ///
+    /// An empty `old_string` creates the file, so `new_string` is appended to the
+    /// (normally absent) content. Returns `None` when `old_string` is not found.
+    #[must_use]
+    pub fn apply(&self, content: Option<String>) -> Option<String> {
+        if self.old_string.is_empty() {
+            return Some(content.unwrap_or_default() + &self.new_string);
+        }
+        let content = content?;
+        if !content.contains(&self.old_string) {
+            return None;
+        }
+        Some(if self.replace_all {
+            content.replace(&self.old_string, &self.new_string)
+        } else {
+            content.replacen(&self.old_string, &self.new_string, 1)
+        })
+    }
+}
+
+/// Input of a `MultiEdit` tool call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultiEditInput {
+    /// File the edits apply to
+    pub file_path: String,
+    /// Edits applied in order, each to the result of the previous one
+    #[serde(default)]
+    pub edits: Vec<EditOperation>,
+}
+
+impl MultiEditInput {
+    /// Parse the input of a `MultiEdit` tool call
+    #[must_use]
+    pub fn from_tool_input<S: std::hash::BuildHasher>(
+        tool_input: &HashMap<String, serde_json::Value, S>,
+    ) -> Option<Self> {
+        parse_tool_input(tool_input)
+    }
+}
+
+/// How a `NotebookEdit` call changes the notebook
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotebookEditMode {
+    /// Replace the source of an existing cell
+    #[default]
+    Replace,
+    /// Insert a new cell after `cell_id` (or at the start)
+    Insert,
+    /// Delete the cell
+    Delete,
+}
+
+impl NotebookEditMode {
+    /// The mode as written in tool input and configuration
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotebookEditMode::Replace => "replace",
+            NotebookEditMode::Insert => "insert",
+            NotebookEditMode::Delete => "delete",
+        }
+    }
+}
+
+/// Input of a `NotebookEdit` tool call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotebookEditInput {
+    /// Notebook being edited
+    pub notebook_path: String,
+    /// Target cell; for inserts, the cell the new cell is placed after
+    #[serde(default)]
+    pub cell_id: Option<String>,
+    /// New cell source (unused for deletes)
+    #[serde(default)]
+    pub new_source: String,
+    /// "code" or "markdown"
+    #[serde(default)]
+    pub cell_type: Option<String>,
+    /// Replace, insert, or delete
+    #[serde(default, alias = "edit_type")]
+    pub edit_mode: NotebookEditMode,
+}
+
+impl NotebookEditInput {
+    /// Parse the input of a `NotebookEdit` tool call
+    #[must_use]
+    pub fn from_tool_input<S: std::hash::BuildHasher>(
+        tool_input: &HashMap<String, serde_json::Value, S>,
+    ) -> Option<Self> {
+        parse_tool_input(tool_input)
+    }
+}
+
 /// Payload for `PostToolUse` hook - fired after Claude executes a tool.
 /// Contains both the input and response data for analysis or logging.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -294,7 +668,6 @@ pub fn validate_base_payload(base: &BasePayload) -> Result<(), String> {
 /// # Errors
 ///
 /// Returns an error if any required field is missing or empty (after trimming whitespace).
-#[allow(dead_code)]
 pub fn validate_permission_request_payload(
     payload: &PermissionRequestPayload,
 ) -> Result<(), String> {
@@ -314,7 +687,6 @@ pub fn validate_permission_request_payload(
 /// # Errors
 ///
 /// Returns an error if any required field is missing or empty (after trimming whitespace).
-#[allow(dead_code)]
 pub fn validate_subagent_start_payload(payload: &SubagentStartPayload) -> Result<(), String> {
     // First validate the base payload
     validate_base_payload(&payload.base)?;
@@ -342,7 +714,6 @@ pub fn validate_subagent_start_payload(payload: &SubagentStartPayload) -> Result
 /// # Errors
 ///
 /// Returns an error if any required field is missing or empty (after trimming whitespace).
-#[allow(dead_code)]
 pub fn validate_subagent_stop_payload(payload: &SubagentStopPayload) -> Result<(), String> {
     // First validate the base payload
     validate_base_payload(&payload.base)?;
@@ -378,6 +749,93 @@ mod tests {
         assert_eq!(result.message, Some("Test blocking message".to_string()));
     }
 
+    #[test]
+    fn test_plain_hook_results_use_exit_code_protocol() {
+        assert!(!HookResult::success().has_json_output());
+        assert!(!HookResult::blocked("nope").has_json_output());
+    }
+
+    #[test]
+    fn test_pre_tool_use_decision_serialization() {
+        let result = HookResult::pre_tool_use_decision(
+            PermissionDecision::Deny,
+            Some("Protected file".to_string()),
+        );
+        assert!(result.has_json_output());
+        assert_eq!(result.blocked, Some(true));
+        assert_eq!(result.message, Some("Protected file".to_string()));
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "hookSpecificOutput": {
+                    "hookEventName": "PreToolUse",
+                    "permissionDecision": "deny",
+                    "permissionDecisionReason": "Protected file"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_permission_request_decision_serialization() {
+        let result = HookResult::permission_request_decision(PermissionBehavior::Allow, None);
+        assert_eq!(result.blocked, Some(false));
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "hookSpecificOutput": {
+                    "hookEventName": "PermissionRequest",
+                    "decision": { "behavior": "allow" }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_common_json_output_fields_serialization() {
+        let result = HookResult::stop_session("Quota exhausted")
+            .with_system_message("Session stopped by conclaude")
+            .with_suppress_output();
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "continue": false,
+                "stopReason": "Quota exhausted",
+                "suppressOutput": true,
+                "systemMessage": "Session stopped by conclaude"
+            })
+        );
+
+        let json = serde_json::to_value(HookResult::decision_block("Lint failed")).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "decision": "block", "reason": "Lint failed" })
+        );
+    }
+
+    #[test]
+    fn test_additional_context_serialization() {
+        let result = HookResult::success().with_hook_specific_output(
+            HookSpecificOutput::additional_context("SessionStart", "branch: main"),
+        );
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "hookSpecificOutput": {
+                    "hookEventName": "SessionStart",
+                    "additionalContext": "branch: main"
+                }
+            })
+        );
+    }
+
     #[test]
     fn test_validate_base_payload() {
         let valid_base = BasePayload {
@@ -955,4 +1413,35 @@ mod tests {
         assert!(json.contains("\"agent_id\":\"coder\""));
         assert!(json.contains("\"subagent_type\":\"implementation\""));
     }
+
+    #[test]
+    fn test_typed_edit_inputs() {
+        let input: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "file_path": "src/lib.rs",
+            "edits": [
+                {"old_string": "a", "new_string": "b"},
+                {"old_string": "c", "new_string": "d", "replace_all": true}
+            ]
+        }))
+        .unwrap();
+        let multi = MultiEditInput::from_tool_input(&input).unwrap();
+        assert_eq!(multi.edits.len(), 2);
+        assert!(multi.edits[1].replace_all);
+        assert_eq!(
+            multi.edits[1].apply(Some("ccx".to_string())).as_deref(),
+            Some("ddx")
+        );
+        assert_eq!(multi.edits[0].apply(Some("xyz".to_string())), None);
+
+        let input: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "notebook_path": "a.ipynb",
+            "cell_id": "abc",
+            "new_source": "",
+            "edit_type": "delete"
+        }))
+        .unwrap();
+        let notebook = NotebookEditInput::from_tool_input(&input).unwrap();
+        assert_eq!(notebook.edit_mode, NotebookEditMode::Delete);
+        assert_eq!(notebook.cell_id.as_deref(), Some("abc"));
+    }
 }